@@ -127,3 +127,48 @@ fn line_peer_error_notify(b: &mut Bencher) {
         black_box(d1);
     })
 }
+
+#[bench]
+fn device_monitor_1000(b: &mut Bencher) {
+    b.iter(|| {
+        let d = Device::new();
+        let children: Vec<Device> = (0..1000).map(|_| Device::new()).collect();
+        for child in children.iter() {
+            child.link(&d, LinkMode::Monitor);
+        }
+        black_box(d);
+    })
+}
+
+#[bench]
+fn device_link_many_1000(b: &mut Bencher) {
+    b.iter(|| {
+        let d = Device::new();
+        let children: Vec<Device> = (0..1000).map(|_| Device::new()).collect();
+        let refs: Vec<&Device> = children.iter().collect();
+        d.link_many(&refs, LinkMode::Notify);
+        black_box(d);
+    })
+}
+
+#[bench]
+fn line_monitor_1000(b: &mut Bencher) {
+    b.iter(|| {
+        let d = Device::new();
+        let children: Vec<Device> = (0..1000).map(|_| Device::new()).collect();
+        for child in children.iter() {
+            d.link_line(child.line(), LinkMode::Monitor).unwrap();
+        }
+        black_box(d);
+    })
+}
+
+#[bench]
+fn line_link_lines_1000(b: &mut Bencher) {
+    b.iter(|| {
+        let d = Device::new();
+        let children: Vec<Device> = (0..1000).map(|_| Device::new()).collect();
+        d.link_lines(children.iter().map(|c| c.line()), LinkMode::Monitor).unwrap();
+        black_box(d);
+    })
+}
@@ -0,0 +1,126 @@
+#![feature(test)]
+
+// Mirrors benches/device.rs, for comparison. The bridged and shared
+// benches include the cost of bridging to thread-safe Devices.
+
+extern crate test;
+use test::{black_box, Bencher};
+
+use async_backplane::*;
+
+#[bench]
+fn create_destroy(b: &mut Bencher) {
+    b.iter(|| {
+        let d = LocalDevice::new();
+        black_box(d);
+    })
+}
+
+#[bench]
+fn create_destroy_bridged(b: &mut Bencher) {
+    b.iter(|| {
+        let d = LocalDevice::new();
+        black_box(d.shared_line());
+        black_box(d);
+    })
+}
+
+#[bench]
+fn device_monitor_drop(b: &mut Bencher) {
+    b.iter(|| {
+        let d1 = LocalDevice::new();
+        let d2 = LocalDevice::new();
+        d1.link(&d2, LinkMode::Monitor);
+        black_box(d1);
+        black_box(d2);
+    })
+}
+
+#[bench]
+fn line_monitor_drop(b: &mut Bencher) {
+    b.iter(|| {
+        let d1 = LocalDevice::new();
+        let d2 = LocalDevice::new();
+        let line = d2.line();
+        d1.link_line(line, LinkMode::Monitor).unwrap();
+        black_box(d1);
+        black_box(d2);
+    })
+}
+
+#[bench]
+fn device_monitor_drop_notify(b: &mut Bencher) {
+    b.iter(|| {
+        let d1 = LocalDevice::new();
+        let d2 = LocalDevice::new();
+        d1.link(&d2, LinkMode::Monitor);
+        black_box(d2);
+        black_box(d1);
+    })
+}
+
+#[bench]
+fn device_monitor_error_notify(b: &mut Bencher) {
+    b.iter(|| {
+        let d1 = LocalDevice::new();
+        let d2 = LocalDevice::new();
+        d1.link(&d2, LinkMode::Monitor);
+        d2.disconnect(Some(Fault::Error));
+        black_box(d1);
+    })
+}
+
+#[bench]
+fn device_peer_drop_notify(b: &mut Bencher) {
+    b.iter(|| {
+        let d1 = LocalDevice::new();
+        let d2 = LocalDevice::new();
+        d1.link(&d2, LinkMode::Peer);
+        black_box(d1);
+        black_box(d2);
+    })
+}
+
+#[bench]
+fn device_peer_error_notify(b: &mut Bencher) {
+    b.iter(|| {
+        let d1 = LocalDevice::new();
+        let d2 = LocalDevice::new();
+        d1.link(&d2, LinkMode::Peer);
+        d2.disconnect(Some(Fault::Error));
+        black_box(d1);
+    })
+}
+
+#[bench]
+fn shared_monitor_error_notify(b: &mut Bencher) {
+    b.iter(|| {
+        let d1 = LocalDevice::new();
+        let d2 = Device::new();
+        d1.link_shared(d2.line(), LinkMode::Monitor).unwrap();
+        d2.disconnect(Some(Fault::Error));
+        black_box(d1);
+    })
+}
+
+#[bench]
+fn shared_monitor_drop(b: &mut Bencher) {
+    b.iter(|| {
+        let d1 = Device::new();
+        let d2 = LocalDevice::new();
+        d1.link_line(d2.shared_line(), LinkMode::Monitor).unwrap();
+        black_box(d1);
+        black_box(d2);
+    })
+}
+
+#[bench]
+fn shared_monitored_error_notify(b: &mut Bencher) {
+    b.iter(|| {
+        let d1 = Device::new();
+        let d2 = LocalDevice::new();
+        d1.link_line(d2.shared_line(), LinkMode::Monitor).unwrap();
+        d2.disconnect(Some(Fault::Error));
+        black_box(d1);
+    })
+}
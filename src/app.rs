@@ -0,0 +1,233 @@
+use crate::scope::{Child, Children};
+use crate::*;
+use futures_lite::{Future, FutureExt, StreamExt};
+use std::fmt::Debug;
+
+type Start = Box<dyn FnOnce(Device) -> Child + Send>;
+
+/// A tree of Devices started in dependency order and stopped in
+/// reverse.
+///
+/// Each child is started by a function which is given a new Device
+/// and should call `Device::ready()` once it has finished
+/// initialising. The next child will not be started until it
+/// has. Children monitor the children they depend on and are all
+/// peered with the Device the application is run with.
+pub struct Application {
+    children: Vec<Spec>,
+}
+
+struct Spec {
+    name: String,
+    after: Vec<String>,
+    start: Start,
+}
+
+/// Something went wrong running an `Application`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AppError {
+    /// The named child depends on a child which was not declared.
+    UnknownDependency(String, String),
+    /// The named child (transitively) depends on itself.
+    Cycle(String),
+    /// More than one child was given the name.
+    DuplicateName(String),
+    /// The named child disconnected before it was ready.
+    NotReady(String, Option<Fault>),
+    /// The named child faulted.
+    Faulted(String, Fault),
+    /// A Device the application is monitoring faulted.
+    Cascade(DeviceID, Fault),
+}
+
+impl Default for Application {
+    fn default() -> Self { Application::new() }
+}
+
+impl Application {
+    /// Creates a new Application with no children.
+    pub fn new() -> Self {
+        Application { children: Vec::new() }
+    }
+
+    /// Declares a child which may only start after the children named
+    /// in `after` are ready.
+    pub fn child<F, Fut>(mut self, name: &str, after: &[&str], start: F) -> Self
+    where
+        F: FnOnce(Device) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.children.push(Spec {
+            name: name.to_string(),
+            after: after.iter().map(|a| a.to_string()).collect(),
+            start: Box::new(move |device| Box::pin(start(device))),
+        });
+        self
+    }
+
+    // Sorts the children such that each comes after its dependencies,
+    // otherwise preserving declaration order.
+    fn order(self) -> Result<Vec<Spec>, AppError> {
+        for (idx, spec) in self.children.iter().enumerate() {
+            if self.children[..idx].iter().any(|s| s.name == spec.name) {
+                return Err(AppError::DuplicateName(spec.name.clone()));
+            }
+            for dep in spec.after.iter() {
+                if !self.children.iter().any(|s| s.name == *dep) {
+                    return Err(AppError::UnknownDependency(spec.name.clone(), dep.clone()));
+                }
+            }
+        }
+        let mut waiting = self.children;
+        let mut ordered: Vec<Spec> = Vec::with_capacity(waiting.len());
+        while !waiting.is_empty() {
+            let next = waiting.iter().position(|spec| {
+                spec.after.iter().all(|dep| ordered.iter().any(|s| s.name == *dep))
+            });
+            match next {
+                Some(idx) => ordered.push(waiting.remove(idx)),
+                None => return Err(AppError::Cycle(on_cycle(&waiting))),
+            }
+        }
+        Ok(ordered)
+    }
+
+    /// Starts the children in dependency order and runs them until
+    /// one of:
+    ///
+    /// * The Device is asked to shut down.
+    /// * A child or a Device we are monitoring faults.
+    /// * All of the children have completed.
+    ///
+    /// Then stops the children still running in reverse order and
+    /// disconnects the Device, with a fault if we are returning one.
+    pub async fn run(self, mut device: Device) -> Result<(), AppError> {
+        let specs = match self.order() {
+            Ok(specs) => specs,
+            Err(e) => {
+                device.disconnect(Some(Fault::Error));
+                return Err(e);
+            }
+        };
+        let mut children = Children::default();
+        let mut spawned: Vec<(String, Line)> = Vec::with_capacity(specs.len());
+        // Why we are stopping, once we are.
+        let mut stop = None;
+        // Start up.
+        'start: for spec in specs {
+            while let Some(message) = device.receive() {
+                stop = handle(&device, &spawned, message);
+                if stop.is_some() { break 'start; }
+            }
+            let child = Device::new();
+            let line = child.line();
+            let links = spec.after.iter().try_for_each(|dep| {
+                let (_, dep) = spawned.iter().find(|(name, _)| name == dep).unwrap();
+                child.link_line(dep.clone(), LinkMode::Monitor)
+            });
+            if links.is_err() {
+                // A dependency went down since it became ready.
+                stop = Some(Err(AppError::NotReady(spec.name.clone(), None)));
+                spawned.push((spec.name, line));
+                break;
+            }
+            device.link(&child, LinkMode::Peer);
+            children.push(line.clone(), (spec.start)(child));
+            spawned.push((spec.name.clone(), line.clone()));
+            // We may be asked to stop while we wait.
+            loop {
+                let message = async { Err(device.next().await) };
+                let ready = async { Ok(line.ready().await) };
+                match children.until(ready.or(message)).await {
+                    Ok(Ok(())) => break,
+                    Ok(Err(ReadyError::Disconnected(fault))) => {
+                        stop = Some(Err(AppError::NotReady(spec.name, fault)));
+                        break 'start;
+                    }
+                    Err(message) => {
+                        let message = message.expect("The Device to still be usable.");
+                        stop = handle(&device, &spawned, message);
+                        if stop.is_some() { break 'start; }
+                    }
+                }
+            }
+        }
+        // Run.
+        while stop.is_none() && spawned.iter().any(|(_, line)| line.is_alive()) {
+            let message = children.until(device.next()).await;
+            let message = message.expect("The Device to still be usable.");
+            stop = handle(&device, &spawned, message);
+        }
+        let ret = stop.unwrap_or(Ok(()));
+        // Shut down.
+        let me = device.device_id();
+        #[allow(unused_must_use)]
+        for (_, line) in spawned.iter().rev() {
+            line.plugboard.send(Shutdown(me));
+            children.until(line.disconnected()).await;
+        }
+        children.finish().await;
+        let fault = match &ret {
+            Ok(()) => None,
+            Err(AppError::Faulted(name, fault)) => {
+                let (_, line) = spawned.iter().find(|(n, _)| n == name).unwrap();
+                Some(Fault::cascade(line.device_id(), *fault))
+            }
+            Err(AppError::NotReady(name, fault)) => {
+                let (_, line) = spawned.iter().find(|(n, _)| n == name).unwrap();
+                Some(Fault::cascade(line.device_id(), fault.unwrap_or(Fault::Error)))
+            }
+            Err(AppError::Cascade(did, fault)) => Some(Fault::cascade(*did, *fault)),
+            Err(_) => Some(Fault::Error),
+        };
+        device.disconnect(fault);
+        ret
+    }
+}
+
+// Returns a result if the message means we should stop.
+fn handle(
+    device: &Device,
+    spawned: &[(String, Line)],
+    message: Message,
+) -> Option<Result<(), AppError>> {
+    match message {
+        Shutdown(_) => Some(Ok(())),
+        Tick(_) => None,
+        Disconnected(sender, None) => {
+            device.forget(sender);
+            None
+        }
+        Disconnected(sender, Some(fault)) => {
+            let name = spawned.iter().find(|(_, line)| line.device_id() == sender);
+            Some(Err(match name {
+                Some((name, _)) => AppError::Faulted(name.clone(), fault),
+                None => AppError::Cascade(sender, fault),
+            }))
+        }
+    }
+}
+
+impl Debug for Application {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<&str> = self.children.iter().map(|s| s.name.as_str()).collect();
+        f.debug_struct("Application").field("children", &names).finish()
+    }
+}
+
+// Names a child on a cycle among those which could not be ordered.
+// Each of them waits on another of them, so following the waits from
+// any one must come back round.
+fn on_cycle(waiting: &[Spec]) -> String {
+    let mut seen: Vec<usize> = Vec::new();
+    let mut idx = 0;
+    while !seen.contains(&idx) {
+        seen.push(idx);
+        idx = waiting[idx]
+            .after
+            .iter()
+            .find_map(|dep| waiting.iter().position(|s| s.name == *dep))
+            .expect("A waiting child to wait on another.");
+    }
+    waiting[idx].name.clone()
+}
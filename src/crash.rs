@@ -1,4 +1,4 @@
-use crate::{panic::Unwind, DeviceID, Fault};
+use crate::{panic::Unwind, DeviceID, Fault, Origin};
 
 /// Something went wrong with a Device.
 #[derive(Debug)]
@@ -11,6 +11,8 @@ pub enum Crash<Error> {
     Error(Error),
     /// A device we depended upon faulted.
     Cascade(DeviceID, Fault),
+    /// We ran out of time.
+    Timeout,
 }
 
 impl<Error> Crash<Error> {
@@ -23,4 +25,56 @@ impl<Error> Crash<Error> {
     /// Did a Device we depend on fault?
     pub fn is_cascade(&self) -> bool { matches!(self, Crash::Cascade(_, _)) }
 
+    /// Did we run out of time?
+    pub fn is_timeout(&self) -> bool { matches!(self, Crash::Timeout) }
+
+    /// For a cascade, where the fault started.
+    pub fn origin(&self) -> Option<Origin> {
+        if let Crash::Cascade(sender, fault) = self {
+            Fault::cascade(*sender, *fault).origin()
+        } else {
+            None
+        }
+    }
+
+    /// Summarises the crash, rendering any panic as text.
+    pub fn summary(&self) -> CrashSummary<Error>
+    where Error: Clone {
+        match self {
+            Crash::PowerOff(did) => CrashSummary::PowerOff(*did),
+            Crash::Panic(unwind) => CrashSummary::Panic(format!("{:#}", unwind)),
+            Crash::Error(err) => CrashSummary::Error(err.clone()),
+            Crash::Cascade(did, fault) => CrashSummary::Cascade(*did, *fault),
+            Crash::Timeout => CrashSummary::Timeout,
+        }
+    }
+}
+
+/// A `Crash` which can be cloned, compared and (with the `serde`
+/// feature) serialised.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CrashSummary<Error> {
+    /// We were asked to shut down.
+    PowerOff(DeviceID),
+    /// The Future we were executing panicked, with this message.
+    Panic(String),
+    /// The Future we were executing returned an Err.
+    Error(Error),
+    /// A device we depended upon faulted.
+    Cascade(DeviceID, Fault),
+    /// We ran out of time.
+    Timeout,
+}
+
+impl<Error> From<Crash<Error>> for CrashSummary<Error> {
+    fn from(crash: Crash<Error>) -> CrashSummary<Error> {
+        match crash {
+            Crash::Error(err) => CrashSummary::Error(err),
+            Crash::PowerOff(did) => CrashSummary::PowerOff(did),
+            Crash::Panic(unwind) => CrashSummary::Panic(format!("{:#}", unwind)),
+            Crash::Cascade(did, fault) => CrashSummary::Cascade(did, fault),
+            Crash::Timeout => CrashSummary::Timeout,
+        }
+    }
 }
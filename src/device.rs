@@ -1,16 +1,24 @@
-use crate::linemap::LineMap;
-use crate::panic::dont_panic;
+use crate::events;
+use crate::linemap::{LineMap, LineOp};
 use crate::plugboard::Plugboard;
-use crate::Watched::{Completed, Messaged};
 use crate::*;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 use concurrent_queue::PopError;
-use futures_lite::{Future, FutureExt, Stream, StreamExt};
-use std::any::Any;
-use std::cell::RefCell;
-use std::fmt::Debug;
-use std::pin::Pin;
-use std::sync::Arc;
-use std::task::{Context, Poll};
+use core::cell::RefCell;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures_core::Stream;
+#[cfg(feature = "std")]
+use {
+    crate::managed,
+    core::any::Any,
+    core::fmt::Debug,
+    core::time::Duration,
+    futures_lite::future::pending,
+    futures_lite::{pin, Future},
+};
 
 /// A Device connects a Future to the backplane.
 #[derive(Debug)]
@@ -24,11 +32,40 @@ pub struct Device {
 
 #[derive(Debug)]
 pub(crate) struct Inner {
+    // Devices we will notify when we disconnect.
     out: LineMap,
+    // Devices which will notify us when they disconnect.
+    ins: LineMap,
     done: bool,
 }
 
 impl Inner {
+    fn apply(&mut self, op: LineOp) {
+        match op {
+            LineOp::Attach(line) => { self.out.attach(line); }
+            LineOp::Detach(did) => { self.out.detach(did); }
+            LineOp::Monitor(line) => { self.ins.attach(line); }
+            LineOp::Unmonitor(did) => { self.ins.detach(did); }
+        }
+    }
+
+    // Apply any changes made through Lines.
+    fn sync(&mut self, plugboard: &Plugboard) {
+        while let Ok(op) = plugboard.line_ops.pop() {
+            self.apply(op);
+        }
+    }
+
+    // Stop tracking a Device once we hear it has disconnected, so we
+    // don't keep its Plugboard alive.
+    fn received(&mut self, plugboard: &Plugboard, message: &Message) {
+        if let Disconnected(did, _) = message {
+            self.sync(plugboard);
+            self.ins.detach(*did);
+            self.out.detach(*did);
+        }
+    }
+
     // Actually send all the messages.
     fn send(&mut self, message: Message) {
         let mut last: Option<Message> = None; // avoid copying
@@ -48,10 +85,23 @@ impl Default for Device {
 impl Device {
     /// Creates a new Device.
     pub fn new() -> Self {
+        let device = Device::with_plugboard(Plugboard::new(None));
+        events::created(device.device_id());
+        device
+    }
+
+    // Carries messages between a LocalDevice and thread-safe Devices
+    // under the LocalDevice's ID, so it announces nothing itself.
+    pub(crate) fn bridge(alias: DeviceID) -> Self {
+        Device::with_plugboard(Plugboard::new(Some(alias)))
+    }
+
+    fn with_plugboard(plugboard: Plugboard) -> Self {
         Device {
-            plugboard: Arc::new(Plugboard::new()),
+            plugboard: Arc::new(plugboard),
             inner: RefCell::new(Inner {
                 out: LineMap::new(),
+                ins: LineMap::new(),
                 done: false,
             }),
         }
@@ -59,7 +109,7 @@ impl Device {
 
     /// Get the ID of this Device.
     pub fn device_id(&self) -> DeviceID {
-        DeviceID::new(&*self.plugboard as *const _ as usize)
+        self.plugboard.device_id()
     }
 
     /// Opens a line to the Device.
@@ -69,37 +119,117 @@ impl Device {
         }
     }
 
+    /// Gives the Device a name for diagnostic purposes.
+    pub fn set_name(&self, name: &str) {
+        *self.plugboard.name.lock() = Some(name.into());
+        events::named(self.device_id(), name);
+    }
+
+    /// The name given with `set_name()`, if any.
+    pub fn name(&self) -> Option<String> {
+        self.plugboard.name.lock().as_deref().map(String::from)
+    }
+
+    /// Announce that we have finished initialising to anyone waiting
+    /// on `Line::ready()`.
+    pub fn ready(&self) {
+        self.plugboard.set_ready();
+    }
+
     /// Notify our peers we're disconnecting.
     pub fn disconnect(self, fault: Option<Fault>) {
         self.do_disconnect(fault);
     }
 
     fn do_disconnect(&self, fault: Option<Fault>) {
-        self.plugboard.close(); // no more requests
+        events::disconnected(self.device_id(), fault);
+        self.shut(fault);
+    }
+
+    // Disconnects without announcing it.
+    pub(crate) fn shut(&self, fault: Option<Fault>) {
+        self.plugboard.close(fault); // no more requests
         let mut inner = self.inner.borrow_mut();
-        while let Ok(op) = self.plugboard.line_ops.pop() {
-            inner.out.apply(op);
-        } // sync
+        inner.done = true; // don't go again on drop
+        inner.sync(&self.plugboard);
         inner.send(Disconnected(self.device_id(), fault));
     }
 
     /// Link with another Device with the provided LinkMode. LinkModes
-    /// are additive, so you can 'upgrade' a link this way.
+    /// are additive, so you can 'upgrade' a link this way. Linking
+    /// again with a mode we already have changes nothing.
     ///
     /// This method is intended for static-style linking, where the
     /// topology is not expected to change. You should not link to a
     /// Device this way after linking to it through a Line.
+    ///
+    /// Panics if you try to link to yourself. See `try_link()`.
     pub fn link(&self, other: &Device, mode: LinkMode) {
-        if self.device_id() == other.device_id() {
+        if self.try_link(other, mode).is_err() {
             panic!("Do not link to yourself!");
         }
+    }
+
+    /// Like `link()`, but fails with `LinkError::SelfLink` instead
+    /// of panicking if you try to link to yourself.
+    pub fn try_link(&self, other: &Device, mode: LinkMode) -> Result<(), LinkError> {
+        if self.device_id() == other.device_id() {
+            return Err(LinkError::SelfLink);
+        }
         if mode.monitor() {
-            other.inner.borrow_mut().out
-                .attach(Line { plugboard: self.plugboard.clone() });
+            other.inner.borrow_mut().out.attach(self.line());
+            self.inner.borrow_mut().ins.attach(other.line());
         }
         if mode.notify() {
-            self.inner.borrow_mut().out
-                .attach(Line { plugboard: other.plugboard.clone() });
+            self.inner.borrow_mut().out.attach(other.line());
+            other.inner.borrow_mut().ins.attach(self.line());
+        }
+        events::linked(self.device_id(), other.device_id(), mode);
+        Ok(())
+    }
+
+    /// Like `link()`, but links with many Devices at once, which is
+    /// much cheaper for a supervisor with lots of children.
+    ///
+    /// Panics without linking any if one of them is us. See
+    /// `try_link_many()`.
+    pub fn link_many(&self, others: &[&Device], mode: LinkMode) {
+        if self.try_link_many(others, mode).is_err() {
+            panic!("Do not link to yourself!");
+        }
+    }
+
+    /// Like `link_many()`, but fails with `LinkError::SelfLink`
+    /// instead of panicking if one of them is us.
+    pub fn try_link_many(&self, others: &[&Device], mode: LinkMode) -> Result<(), LinkError> {
+        let did = self.device_id();
+        if others.iter().any(|other| other.device_id() == did) {
+            return Err(LinkError::SelfLink);
+        }
+        let mut lines = Vec::with_capacity(others.len());
+        for other in others {
+            let mut theirs = other.inner.borrow_mut();
+            if mode.monitor() { theirs.out.attach(self.line()); }
+            if mode.notify() { theirs.ins.attach(self.line()); }
+            lines.push(other.line());
+        }
+        self.attach_all(lines, mode);
+        for other in others {
+            events::linked(did, other.device_id(), mode);
+        }
+        Ok(())
+    }
+
+    // Attach lines we have already plugged into on the other end.
+    fn attach_all(&self, lines: Vec<Line>, mode: LinkMode) {
+        let mut inner = self.inner.borrow_mut();
+        match mode {
+            LinkMode::Monitor => { inner.ins.attach_all(lines); }
+            LinkMode::Notify => { inner.out.attach_all(lines); }
+            LinkMode::Peer => {
+                inner.ins.attach_all(lines.clone());
+                inner.out.attach_all(lines);
+            }
         }
     }
 
@@ -109,53 +239,211 @@ impl Device {
     /// This method is intended for static-style linking, where the
     /// topology is not expected to change. You should not link to a
     /// Device this way after linking to it through a Line.
+    ///
+    /// Panics if you try to unlink from yourself. See `try_unlink()`.
     pub fn unlink(&self, other: &Device, mode: LinkMode) {
-        if self.device_id() == other.device_id() {
+        if self.try_unlink(other, mode).is_err() {
             panic!("Do not link to yourself!");
         }
+    }
+
+    /// Like `unlink()`, but fails with `LinkError::SelfLink` instead
+    /// of panicking if you try to unlink from yourself.
+    pub fn try_unlink(&self, other: &Device, mode: LinkMode) -> Result<(), LinkError> {
+        if self.device_id() == other.device_id() {
+            return Err(LinkError::SelfLink);
+        }
         if mode.monitor() {
             other.inner.borrow_mut().out.detach(self.device_id());
+            self.inner.borrow_mut().ins.detach(other.device_id());
         }
         if mode.notify() {
             self.inner.borrow_mut().out.detach(other.device_id());
+            other.inner.borrow_mut().ins.detach(self.device_id());
         }
+        events::unlinked(self.device_id(), other.device_id(), mode);
+        Ok(())
     }
 
     /// Link with a line. This is safer than linking directly to a
-    /// Device, but a little slower.
-    pub fn link_line(&self, other: Line, mode: LinkMode) -> Result<(), LinkError>{
+    /// Device, but a little slower. As with `link()`, linking again
+    /// with a mode we already have changes nothing. Fails with
+    /// `LinkError::LinkDown` if the other Device has already
+    /// disconnected.
+    ///
+    /// Panics if you try to link to yourself. See `try_link_line()`.
+    pub fn link_line(&self, other: Line, mode: LinkMode) -> Result<(), LinkError> {
+        match self.try_link_line(other, mode) {
+            Err(LinkError::SelfLink) => panic!("Do not link to yourself!"),
+            ret => ret,
+        }
+    }
+
+    /// Like `link_line()`, but fails with `LinkError::SelfLink`
+    /// instead of panicking if you try to link to yourself.
+    pub fn try_link_line(&self, other: Line, mode: LinkMode) -> Result<(), LinkError> {
         if self.device_id() == other.device_id() {
-            panic!("Do not link to yourself!");
+            return Err(LinkError::SelfLink);
         }
+        let did = other.device_id();
         if mode.monitor() {
             other.plugboard.plug(self.line(), LinkError::LinkDown)?;
+            self.inner.borrow_mut().ins.attach(other.clone());
         }
         if mode.notify() {
+            other.plugboard.monitor(self.line(), LinkError::LinkDown)?;
             self.inner.borrow_mut().out.attach(other);
         }
+        events::linked(self.device_id(), did, mode);
+        Ok(())
+    }
+
+    /// Like `link_line()`, but links with many lines at once. Lines
+    /// whose Devices have already disconnected are skipped and
+    /// returned in the error.
+    ///
+    /// Panics without linking any if one of them is ours. See
+    /// `try_link_lines()`.
+    pub fn link_lines<I>(&self, others: I, mode: LinkMode) -> Result<(), Vec<Line>>
+    where
+        I: IntoIterator<Item = Line>,
+    {
+        match self.try_link_lines(others, mode) {
+            Ok(down) if down.is_empty() => Ok(()),
+            Ok(down) => Err(down),
+            Err(_) => panic!("Do not link to yourself!"),
+        }
+    }
+
+    /// Like `link_lines()`, but fails with `LinkError::SelfLink`
+    /// instead of panicking if one of them is ours. Returns the lines
+    /// skipped because their Devices had already disconnected.
+    pub fn try_link_lines<I>(&self, others: I, mode: LinkMode) -> Result<Vec<Line>, LinkError>
+    where
+        I: IntoIterator<Item = Line>,
+    {
+        let did = self.device_id();
+        let others: Vec<Line> = others.into_iter().collect();
+        if others.iter().any(|other| other.device_id() == did) {
+            return Err(LinkError::SelfLink);
+        }
+        let mut lines = Vec::with_capacity(others.len());
+        let mut down = Vec::new();
+        for other in others {
+            if self.plug_into(&other, mode).is_ok() {
+                lines.push(other);
+            } else {
+                down.push(other);
+            }
+        }
+        for other in lines.iter() {
+            events::linked(did, other.device_id(), mode);
+        }
+        self.attach_all(lines, mode);
+        Ok(down)
+    }
+
+    // Records our side of the link with the other Device, failing if
+    // it has already disconnected.
+    fn plug_into(&self, other: &Line, mode: LinkMode) -> Result<(), LinkError> {
+        if mode.monitor() { other.plugboard.plug(self.line(), LinkError::LinkDown)?; }
+        if mode.notify() { other.plugboard.monitor(self.line(), LinkError::LinkDown)?; }
         Ok(())
     }
 
     /// Unlink with a line. This is safer than linking directly to a
     /// Device, but a little slower.
-    pub fn unlink_line(&self, other: &Line, mode: LinkMode) {
+    ///
+    /// Fails with `LinkError::LinkDown` if the other Device has
+    /// already disconnected, or `LinkError::NotLinked` if we were not
+    /// linked in the given mode. Either way, we are no longer linked.
+    ///
+    /// Panics if you try to unlink from yourself. See `try_unlink_line()`.
+    pub fn unlink_line(&self, other: &Line, mode: LinkMode) -> Result<(), LinkError> {
+        match self.try_unlink_line(other, mode) {
+            Err(LinkError::SelfLink) => panic!("Do not link to yourself!"),
+            ret => ret,
+        }
+    }
+
+    /// Like `unlink_line()`, but fails with `LinkError::SelfLink`
+    /// instead of panicking if you try to unlink from yourself.
+    pub fn try_unlink_line(&self, other: &Line, mode: LinkMode) -> Result<(), LinkError> {
         if self.device_id() == other.device_id() {
-            panic!("Do not link to yourself!");
+            return Err(LinkError::SelfLink);
         }
-        #[allow(unused_must_use)]
+        let did = other.device_id();
+        let mut ret = Ok(());
+        let mut found = false;
+        let mut inner = self.inner.borrow_mut();
+        inner.sync(&self.plugboard); // it may have been linked through a Line
         if mode.monitor() {
-            other.plugboard.unplug(self.device_id(), LinkError::LinkDown);
+            if let Err(e) = other.plugboard.unplug(self.device_id(), LinkError::LinkDown) {
+                ret = Err(e);
+            }
+            found |= inner.ins.detach(did);
         }
         if mode.notify() {
-            self.inner.borrow_mut().out.detach(other.device_id());
+            if let Err(e) = other.plugboard.unmonitor(self.device_id(), LinkError::LinkDown) {
+                ret = Err(e);
+            }
+            found |= inner.out.detach(did);
+        }
+        drop(inner);
+        events::unlinked(self.device_id(), did, mode);
+        if ret.is_ok() && !found { Err(LinkError::NotLinked) } else { ret }
+    }
+
+    // Stop notifying a Device which has disconnected.
+    #[cfg(feature = "std")]
+    pub(crate) fn forget(&self, did: DeviceID) {
+        let mut inner = self.inner.borrow_mut();
+        inner.sync(&self.plugboard);
+        inner.out.detach(did);
+        inner.ins.detach(did);
+    }
+
+    /// Lists the Devices we are linked to and how, including links
+    /// made through Lines. Devices which have already disconnected
+    /// are omitted.
+    ///
+    /// There is no `Line` equivalent: our links live with us and
+    /// links made through Lines sit in a queue only we can read.
+    pub fn links(&self) -> Vec<(DeviceID, LinkMode)> {
+        let mut inner = self.inner.borrow_mut();
+        inner.sync(&self.plugboard);
+        let mut links: Vec<(DeviceID, LinkMode)> = Vec::new();
+        let lines = inner.ins.iter().chain(inner.out.iter());
+        for line in lines.filter(|l| l.is_alive()) {
+            let did = line.device_id();
+            if let Some(mode) = link_mode(&inner, did) {
+                if !links.iter().any(|(d, _)| *d == did) {
+                    links.push((did, mode));
+                }
+            }
         }
+        links
+    }
+
+    /// Returns how we are linked to the Device on the other end of
+    /// the Line, if at all.
+    pub fn link_mode(&self, other: &Line) -> Option<LinkMode> {
+        let mut inner = self.inner.borrow_mut();
+        inner.sync(&self.plugboard);
+        link_mode(&inner, other.device_id())
     }
 
     /// Attempts to get the next message. Does not wait for one to arrive.
     pub fn receive(&self) -> Option<Message> {
-        self.plugboard.messages.try_pop().ok()
+        let message = self.plugboard.receive().ok()?;
+        self.inner.borrow_mut().received(&self.plugboard, &message);
+        Some(message)
     }
+}
 
+// Supervision needs to catch panics, which needs std.
+#[cfg(feature = "std")]
+impl Device {
     /// Returns the first of (with a bias towards the former):
     /// * The next message to be received.
     /// * The result of the completed future.
@@ -166,18 +454,26 @@ impl Device {
         F::Output: Debug,
         C: 'static + Any + Debug + Send,
     {
-        let fut = dont_panic(f);
-        async {
-            let message = self.next().await.expect("The Device to still be usable.");
-            Ok(Messaged(message))
-        }
-        .or(async {
-            match fut.await {
-                Ok(val) => Ok(Completed(val)),
-                Err(unwind) => Err(Crash::Panic(unwind)),
-            }
-        })
-        .await
+        managed::watch(self, f).await
+    }
+
+    /// Like `watch()`, but gives up with `Crash::Timeout` if neither
+    /// a message arrives nor the future completes before the timeout.
+    pub async fn watch_timeout<F, C, T>(
+        &mut self,
+        f: F,
+        timer: &T,
+        timeout: Duration,
+    ) -> Result<Watched<<F as Future>::Output>, Crash<C>>
+    where
+        F: Future + Unpin,
+        F::Output: Debug,
+        C: 'static + Any + Debug + Send,
+        T: Timer,
+    {
+        let delay = timer.delay(timeout);
+        pin!(delay);
+        managed::watch_until(self, f, delay).await
     }
 
     /// Runs an async closure while monitoring for messages. Messages
@@ -186,7 +482,7 @@ impl Device {
     /// * Disconnects without fault are ignored.
     /// * Disconnects with fault cause the Device to fault.
     /// * Requests to disconnect cause the Device to crash but
-    /// announce a successful completion.
+    ///   announce a successful completion.
     ///
     /// If the provided closure returns successfully, the result is
     /// returned along with the Device for re-use. Monitors will *not*
@@ -195,42 +491,33 @@ impl Device {
     /// If the Device faults, either because the provided closure
     /// returned an Err variant or because a fault was propagated,
     /// announces our fault to our monitors.
-    pub async fn part_manage<'a, F, T, C>(mut self, mut f: F) -> Result<(Device, T), Crash<C>>
+    pub async fn part_manage<F, T, C>(self, f: F) -> Result<(Device, T), Crash<C>>
     where
         F: Future<Output = Result<T, C>> + Unpin,
         C: 'static + Debug + Send,
         T: Debug,
     {
-        loop {
-            match self.watch(&mut f).await {
-                Ok(Completed(Ok(val))) => {
-                    return Ok((self, val));
-                }
-                Ok(Completed(Err(val))) => {
-                    self.disconnect(Some(Fault::Error));
-                    return Err(Crash::Error(val));
-                }
-                Ok(Messaged(Disconnected(sender, Some(fault)))) => {
-                    self.disconnect(Some(Fault::Cascade(sender)));
-                    return Err(Crash::Cascade(sender, fault));
-                }
-                Ok(Messaged(Disconnected(sender, None))) => {
-                    #[allow(unused_must_use)]
-                    if !self.inner.borrow_mut().out.detach(sender) {
-                        self.plugboard.unplug(sender, LinkError::LinkDown);
-                    }
-                    continue;
-                }
-                Ok(Messaged(Shutdown(id))) => {
-                    self.disconnect(None);
-                    return Err(Crash::PowerOff(id));
-                }
-                Err(crash) => {
-                    self.disconnect(Some(Fault::Error));
-                    return Err(crash);
-                }
-            }
-        }
+        managed::part_manage_until(self, f, pending()).await
+    }
+
+    /// Like `part_manage()`, but if the provided future has not
+    /// completed before the timeout, crashes with `Crash::Timeout`
+    /// and announces `Fault::Timeout` to our monitors.
+    pub async fn part_manage_timeout<F, T, C, R>(
+        self,
+        f: F,
+        timer: &R,
+        timeout: Duration,
+    ) -> Result<(Device, T), Crash<C>>
+    where
+        F: Future<Output = Result<T, C>> + Unpin,
+        C: 'static + Debug + Send,
+        T: Debug,
+        R: Timer,
+    {
+        let delay = timer.delay(timeout);
+        pin!(delay);
+        managed::part_manage_until(self, f, delay).await
     }
 
     /// Like `part_manage()`, but in the case of successful completion
@@ -241,13 +528,74 @@ impl Device {
         C: 'static + Debug + Send,
         T: Debug,
     {
-        match self.part_manage(f).await {
-            Ok((device, val)) => {
-                device.disconnect(None);
-                Ok(val)
-            }
-            Err(e) => Err(e),
-        }
+        managed::manage_until(self, f, pending()).await
+    }
+
+    /// Like `manage()`, but if the provided future has not completed
+    /// before the timeout, crashes with `Crash::Timeout` and announces
+    /// `Fault::Timeout` to our monitors.
+    pub async fn manage_timeout<F, C, T, R>(
+        self,
+        f: F,
+        timer: &R,
+        timeout: Duration,
+    ) -> Result<T, Crash<C>>
+    where
+        F: Future<Output = Result<T, C>> + Unpin,
+        C: 'static + Debug + Send,
+        T: Debug,
+        R: Timer,
+    {
+        let delay = timer.delay(timeout);
+        pin!(delay);
+        managed::manage_until(self, f, delay).await
+    }
+}
+
+#[cfg(feature = "std")]
+impl managed::Managed for Device {
+    type Line = Line;
+
+    fn line(&self) -> Line { Device::line(self) }
+
+    fn device_id(&self) -> DeviceID { Device::device_id(self) }
+
+    fn disconnect(self, fault: Option<Fault>) { Device::disconnect(self, fault) }
+
+    fn forget(&self, did: DeviceID) { Device::forget(self, did) }
+
+    fn crashed<C: Debug>(line: &Line, crash: &Crash<C>) {
+        events::crashed(line.device_id(), line.name(), crash);
+    }
+}
+
+#[cfg(feature = "std")]
+impl Device {
+    /// Runs an async closure with a `Scope` it can spawn children
+    /// into. Each child runs in managed mode on a new Device peered
+    /// with this one. Messages are handled as in `part_manage()`.
+    ///
+    /// However the scope is left - by the closure returning, by its
+    /// panicking or by a message - any children still running are
+    /// asked to shut down and have finished before we return. Unlike
+    /// `part_manage()`, this Device is not disconnected on failure.
+    pub async fn scope<F, Fut, T, C>(&mut self, f: F) -> Result<T, Crash<C>>
+    where
+        F: FnOnce(Scope) -> Fut,
+        Fut: Future<Output = Result<T, C>>,
+        C: 'static + Debug + Send,
+        T: Debug,
+    {
+        scope::run(self, f).await
+    }
+}
+
+fn link_mode(inner: &Inner, did: DeviceID) -> Option<LinkMode> {
+    match (inner.ins.contains(did), inner.out.contains(did)) {
+        (true, true) => Some(LinkMode::Peer),
+        (true, false) => Some(LinkMode::Monitor),
+        (false, true) => Some(LinkMode::Notify),
+        (false, false) => None,
     }
 }
 
@@ -255,10 +603,9 @@ impl Drop for Device {
     fn drop(&mut self) {
         let mut inner = self.inner.borrow_mut();
         if !inner.done {
-            self.plugboard.close(); // no more requests
-            while let Ok(op) = self.plugboard.line_ops.pop() {
-                inner.out.apply(op);
-            } // sync
+            events::disconnected(self.device_id(), Some(Fault::Drop));
+            self.plugboard.close(Some(Fault::Drop)); // no more requests
+            inner.sync(&self.plugboard);
             inner.send(Disconnected(self.device_id(), Some(Fault::Drop)));
         }
     }
@@ -272,13 +619,19 @@ impl Stream for Device {
         let this = self.get_mut();
         let mut inner = this.inner.borrow_mut();
         if !inner.done {
-            match this.plugboard.messages.try_pop() {
-                Ok(val) => Poll::Ready(Some(val)),
+            match this.plugboard.receive() {
+                Ok(val) => {
+                    inner.received(&this.plugboard, &val);
+                    Poll::Ready(Some(val))
+                }
                 Err(PopError::Empty) => {
-                    this.plugboard.messages.register(ctx.waker());
+                    this.plugboard.waker.register(ctx.waker());
                     // Make sure we don't lose out in a race
-                    match this.plugboard.messages.try_pop() {
-                        Ok(val) => Poll::Ready(Some(val)), // Sorry for leaving a waker
+                    match this.plugboard.receive() {
+                        Ok(val) => {
+                            inner.received(&this.plugboard, &val);
+                            Poll::Ready(Some(val)) // Sorry for leaving a waker
+                        }
                         Err(PopError::Empty) => Poll::Pending,
                         Err(PopError::Closed) => {
                             inner.done = true;
@@ -1,15 +1,34 @@
 pub use core::convert::From;
 use core::fmt;
 
-/// A locally unique identifier for a Device.
+/// A locally unique identifier for a Device. Devices in other
+/// processes we are connected to are qualified by their node.
+///
+/// With the `serde` feature, it serialises as its node and a number.
+/// The number is only meaningful to the process it came from while
+/// the Device is alive, so deserialising one is only good for
+/// comparing with others from the same place.
 #[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DeviceID {
+    pub(crate) node: u32,
+    #[cfg_attr(feature = "serde", serde(rename = "id"))]
     pub(crate) inner: usize,
 }
 
 impl DeviceID {
     pub(crate) fn new(inner: usize) -> DeviceID {
-        DeviceID { inner }
+        DeviceID { node: 0, inner }
+    }
+
+    /// The node the Device lives on, or 0 for this process.
+    pub fn node(&self) -> u32 {
+        self.node
+    }
+
+    /// true if the Device lives in this process.
+    pub fn is_local(&self) -> bool {
+        self.node == 0
     }
 }
 
@@ -21,12 +40,22 @@ impl From<DeviceID> for usize {
 
 impl fmt::Debug for DeviceID {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_fmt(format_args!("DeviceID<{:x}>", self.inner))
+        f.write_fmt(format_args!("DeviceID<{:x}>", self))
     }
 }
 
 impl fmt::Display for DeviceID {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_fmt(format_args!("DeviceID<{:x}>", self.inner))
+        f.write_fmt(format_args!("DeviceID<{:x}>", self))
+    }
+}
+
+impl fmt::LowerHex for DeviceID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_local() {
+            f.write_fmt(format_args!("{:x}", self.inner))
+        } else {
+            f.write_fmt(format_args!("{}:{:x}", self.node, self.inner))
+        }
     }
 }
@@ -0,0 +1,124 @@
+// Lifecycle events. These are the hooks through which the optional
+// integrations, the registry, crash reports, metrics and any
+// installed observer hear about what Devices are up to.
+//
+// Without std, only the optional integrations are left to listen.
+#![cfg_attr(not(feature = "std"), allow(unused_variables))]
+#[cfg(feature = "metrics")]
+use crate::metrics;
+#[cfg(feature = "std")]
+use crate::{observer::notify, registry, report};
+use crate::*;
+#[cfg(feature = "std")]
+use core::{fmt::Debug, future::Future};
+
+#[cfg(feature = "tracing")]
+use tracing::{debug, trace, warn};
+#[cfg(all(feature = "std", feature = "tracing"))]
+use tracing::{error, Instrument};
+
+#[inline(always)]
+pub(crate) fn created(did: DeviceID) {
+    #[cfg(feature = "tracing")]
+    trace!(device_id = %did, "created");
+    #[cfg(feature = "metrics")]
+    metrics::created();
+    #[cfg(feature = "std")]
+    {
+        registry::created(did);
+        notify(|o| o.created(did));
+    }
+}
+
+#[inline(always)]
+pub(crate) fn named(did: DeviceID, name: &str) {
+    #[cfg(feature = "tracing")]
+    trace!(device_id = %did, name = name, "named");
+    #[cfg(feature = "std")]
+    registry::named(did, name);
+}
+
+#[inline(always)]
+pub(crate) fn linked(did: DeviceID, other: DeviceID, mode: LinkMode) {
+    #[cfg(feature = "tracing")]
+    trace!(device_id = %did, other = %other, mode = ?mode, "linked");
+    #[cfg(feature = "std")]
+    {
+        registry::linked(did, other, mode);
+        notify(|o| o.linked(did, other, mode));
+    }
+}
+
+#[inline(always)]
+pub(crate) fn unlinked(did: DeviceID, other: DeviceID, mode: LinkMode) {
+    #[cfg(feature = "tracing")]
+    trace!(device_id = %did, other = %other, mode = ?mode, "unlinked");
+    #[cfg(feature = "std")]
+    {
+        registry::unlinked(did, other, mode);
+        notify(|o| o.unlinked(did, other, mode));
+    }
+}
+
+#[inline(always)]
+pub(crate) fn sent(did: DeviceID, message: &Message, ok: bool) {
+    #[cfg(feature = "metrics")]
+    metrics::sent(ok);
+    if ok {
+        #[cfg(feature = "tracing")]
+        trace!(device_id = %did, message = ?message, "sent");
+    } else {
+        #[cfg(feature = "tracing")]
+        debug!(device_id = %did, message = ?message, "send failed");
+        #[cfg(feature = "std")]
+        notify(|o| o.send_failed(did, message));
+    }
+}
+
+// Some messages left a mailbox, either received or thrown away.
+#[inline(always)]
+pub(crate) fn dequeued(_count: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::dequeued(_count);
+}
+
+#[inline(always)]
+pub(crate) fn disconnected(did: DeviceID, fault: Option<Fault>) {
+    #[cfg(feature = "tracing")]
+    match fault {
+        None => debug!(device_id = %did, "disconnected"),
+        Some(fault) => warn!(device_id = %did, fault = ?fault, "disconnected"),
+    }
+    #[cfg(feature = "metrics")]
+    metrics::disconnected(fault);
+    #[cfg(feature = "std")]
+    {
+        registry::disconnected(did);
+        notify(|o| o.disconnected(did, fault));
+    }
+}
+
+#[cfg(feature = "std")]
+#[inline(always)]
+pub(crate) fn crashed<C: Debug>(did: DeviceID, name: Option<String>, crash: &Crash<C>) {
+    #[cfg(feature = "tracing")]
+    match crash {
+        Crash::PowerOff(by) => debug!(device_id = %did, by = %by, "powered off"),
+        Crash::Panic(unwind) => error!(device_id = %did, unwind = ?unwind, "panicked"),
+        Crash::Error(err) => error!(device_id = %did, error = ?err, "errored"),
+        Crash::Cascade(by, fault) => {
+            warn!(device_id = %did, by = %by, fault = ?fault, "cascaded")
+        }
+        Crash::Timeout => warn!(device_id = %did, "timed out"),
+    }
+    report::crashed(did, name, crash);
+}
+
+// Runs a managed future in a span identifying the Device.
+#[cfg(feature = "std")]
+#[inline(always)]
+pub(crate) fn managing<F: Future>(_did: DeviceID, f: F) -> impl Future<Output = F::Output> {
+    #[cfg(feature = "tracing")]
+    let f = f.instrument(tracing::debug_span!("managed", device_id = %_did));
+    f
+}
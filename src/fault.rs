@@ -2,16 +2,66 @@ use crate::DeviceID;
 
 /// The device has disconnected and it wasn't for a good reason.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Fault {
     /// Wasn't scheduled on an executor.
     Drop,
     /// Return an Err or panicked or generally something bad.
     Error,
-    /// A device we depended on faulted.
-    Cascade(DeviceID),
+    /// Ran out of time.
+    Timeout,
+    /// We lost the connection to the node the Device lives on.
+    ConnectionLost,
+    /// The node the Device lives on stopped answering heartbeats.
+    NodeDown,
+    /// A device we depended on faulted. Also carries where the fault
+    /// started.
+    Cascade(DeviceID, Origin),
+}
+
+/// The Device a cascade started with and why.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Origin {
+    /// The Device which faulted first.
+    pub device: DeviceID,
+    /// What it faulted with.
+    pub cause: Cause,
+    /// How many Devices the fault cascaded through to get here.
+    pub hops: usize,
+}
+
+/// The kind of fault a cascade started with.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Cause {
+    /// See `Fault::Drop`.
+    Drop,
+    /// See `Fault::Error`.
+    Error,
+    /// See `Fault::Timeout`.
+    Timeout,
+    /// See `Fault::ConnectionLost`.
+    ConnectionLost,
+    /// See `Fault::NodeDown`.
+    NodeDown,
 }
 
 impl Fault {
+    /// The fault to disconnect with when a Device we depended on
+    /// faulted with the provided fault.
+    pub fn cascade(sender: DeviceID, fault: Fault) -> Fault {
+        let origin = match fault {
+            Fault::Cascade(_, origin) => Origin { hops: origin.hops + 1, ..origin },
+            Fault::Drop => Origin { device: sender, cause: Cause::Drop, hops: 1 },
+            Fault::Error => Origin { device: sender, cause: Cause::Error, hops: 1 },
+            Fault::Timeout => Origin { device: sender, cause: Cause::Timeout, hops: 1 },
+            Fault::ConnectionLost => Origin { device: sender, cause: Cause::ConnectionLost, hops: 1 },
+            Fault::NodeDown => Origin { device: sender, cause: Cause::NodeDown, hops: 1 },
+        };
+        Fault::Cascade(sender, origin)
+    }
+
     /// Did the Device drop without being scheduled?
     pub fn is_drop(&self) -> bool {
         *self == Fault::Drop
@@ -22,7 +72,27 @@ impl Fault {
         *self == Fault::Error
     }
 
+    /// Did we run out of time?
+    pub fn is_timeout(&self) -> bool {
+        *self == Fault::Timeout
+    }
+
+    /// Did we lose the connection to the Device's node?
+    pub fn is_connection_lost(&self) -> bool {
+        *self == Fault::ConnectionLost
+    }
+
+    /// Did the Device's node stop answering?
+    pub fn is_node_down(&self) -> bool {
+        *self == Fault::NodeDown
+    }
+
     /// Are we a cascade fault?
-    pub fn is_cascade(&self) -> bool { matches!(self, Fault::Cascade(_)) }
+    pub fn is_cascade(&self) -> bool { matches!(self, Fault::Cascade(_, _)) }
+
+    /// Where a cascade fault started.
+    pub fn origin(&self) -> Option<Origin> {
+        if let Fault::Cascade(_, origin) = self { Some(*origin) } else { None }
+    }
 
 }
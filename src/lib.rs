@@ -1,10 +1,34 @@
 #![deny(clippy::all)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+#[cfg(not(any(feature = "std", feature = "spin")))]
+compile_error!("Without the `std` feature, enable `spin` for the locks we need.");
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "node")]
+pub mod node;
+#[cfg(feature = "std")]
+pub mod observer;
+#[cfg(feature = "std")]
 pub mod panic;
 pub mod prelude;
+#[cfg(feature = "std")]
+pub mod registry;
+#[cfg(feature = "std")]
+pub mod report;
+
+#[cfg(feature = "std")]
+mod app;
+#[cfg(feature = "std")]
+pub use app::{AppError, Application};
 
+#[cfg(feature = "std")]
 mod crash;
-pub use crash::Crash;
+#[cfg(feature = "std")]
+pub use crash::{Crash, CrashSummary};
 
 mod device_id;
 pub use device_id::DeviceID;
@@ -13,27 +37,66 @@ mod device;
 pub use device::Device;
 
 mod fault;
-pub use fault::Fault;
+pub use fault::{Cause, Fault, Origin};
 
 mod line;
 pub use line::Line;
 
+mod local;
+pub use local::{LocalDevice, LocalLine};
+
+#[cfg(feature = "std")]
+mod scope;
+#[cfg(feature = "std")]
+pub use scope::Scope;
+
+#[cfg(feature = "test-support")]
+pub mod sim;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+
+#[cfg(feature = "std")]
+pub mod timer;
+#[cfg(feature = "std")]
+pub use timer::Timer;
+
 mod watched;
 pub use watched::Watched;
 
+mod events;
 mod linemap;
+#[cfg(feature = "std")]
+mod managed;
 mod plugboard;
+#[cfg(feature = "test-support")]
+mod rng;
+#[cfg(feature = "node")]
+mod wire;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// There was a problem Linking
 pub enum LinkError {
     /// We can't because we already disconnected.
     DeviceDown,
     /// We can't because the other Device already disconnected.
     LinkDown,
+    /// We can't because the other Device is us.
+    SelfLink,
+    /// We can't unlink because we weren't linked.
+    NotLinked,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A Device never became ready.
+pub enum ReadyError {
+    /// It disconnected first, with the provided fault, if any.
+    Disconnected(Option<Fault>),
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u32)]
 /// How deeply should we link?
 pub enum LinkMode {
@@ -61,15 +124,49 @@ impl LinkMode {
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A message exchanged between devices.
 pub enum Message {
     /// A Device we are monitoring has disconnected.
     Disconnected(DeviceID, Option<Fault>),
     /// Request to stop running.
     Shutdown(DeviceID),
+    /// A timer the Device set went off. Carries the Device's own ID.
+    Tick(DeviceID),
 }
 
-use Message::{Disconnected, Shutdown};
+use Message::{Disconnected, Shutdown, Tick};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A message could not be sent. Either way, you get it back.
+pub enum SendError {
+    /// The Device has disconnected.
+    Closed(Message),
+    /// The Device's mailbox is full. Mailboxes are currently
+    /// unbounded, so this does not happen yet.
+    Full(Message),
+}
+
+impl SendError {
+    /// true if the Device has disconnected.
+    pub fn is_closed(&self) -> bool {
+        matches!(self, SendError::Closed(_))
+    }
+
+    /// true if the Device's mailbox is full.
+    pub fn is_full(&self) -> bool {
+        matches!(self, SendError::Full(_))
+    }
+
+    /// Returns the message we failed to send.
+    pub fn into_message(self) -> Message {
+        match self {
+            SendError::Closed(message) => message,
+            SendError::Full(message) => message,
+        }
+    }
+}
 
 impl Message {
     /// Returns the DeviceID of the sender.
@@ -77,6 +174,7 @@ impl Message {
         match self {
             Disconnected(did, _) => *did,
             Shutdown(did) => *did,
+            Tick(did) => *did,
         }
     }
 
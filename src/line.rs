@@ -1,7 +1,10 @@
+use crate::events;
 use crate::plugboard::Plugboard;
 use crate::*;
 use core::fmt;
-use std::sync::Arc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::future::poll_fn;
 
 /// A reference to a `Device` that allows us to link with it.
 #[derive(Clone)]
@@ -12,43 +15,108 @@ pub struct Line {
 impl Line {
     /// Get the ID of the Device this line is connected to.
     pub fn device_id(&self) -> DeviceID {
-        DeviceID::new(&*self.plugboard as *const _ as usize)
+        self.plugboard.device_id()
+    }
+
+    /// The name the Device was given with `Device::set_name()`, if any.
+    pub fn name(&self) -> Option<String> {
+        self.plugboard.name.lock().as_deref().map(String::from)
+    }
+
+    /// true if the Device has not yet disconnected.
+    pub fn is_alive(&self) -> bool {
+        self.plugboard.is_open()
+    }
+
+    /// Waits for the Device to disconnect, returning the fault it
+    /// disconnected with, if any. Does not require linking.
+    pub async fn disconnected(&self) -> Option<Fault> {
+        poll_fn(|ctx| self.plugboard.poll_disconnected(ctx)).await
+    }
+
+    /// true if the Device has announced it is ready.
+    pub fn is_ready(&self) -> bool {
+        self.plugboard.is_ready()
+    }
+
+    /// Waits for the Device to announce it is ready with
+    /// `Device::ready()`. Fails if it disconnects first.
+    pub async fn ready(&self) -> Result<(), ReadyError> {
+        poll_fn(|ctx| self.plugboard.poll_ready(ctx)).await
     }
 
     /// Send a message to the Device. Returns the original message on
     /// failure (if the Device has disconnected).
-    pub fn send(self, message: Message) -> Result<(), Message> {
+    pub fn send(&self, message: Message) -> Result<(), Message> {
         self.plugboard.send(message)
     }
 
+    /// Like `send()`, but says why we failed.
+    pub fn try_send(&self, message: Message) -> Result<(), SendError> {
+        self.plugboard.try_send(message)
+    }
+
+    /// Sends a message to every Line, returning how many Devices it
+    /// was delivered to.
+    pub fn broadcast<'a, I>(lines: I, message: Message) -> usize
+    where
+        I: IntoIterator<Item = &'a Line>,
+    {
+        lines.into_iter().filter(|line| line.send(message).is_ok()).count()
+    }
+
     /// Links with a Device through its Line. Panics if you try to link to yourself.
     pub fn link_line(&self, other: Line, mode: LinkMode) -> Result<(), LinkError> {
+        match self.try_link_line(other, mode) {
+            Err(LinkError::SelfLink) => panic!("Do not link to yourself."),
+            ret => ret,
+        }
+    }
+
+    /// Like `link_line()`, but fails with `LinkError::SelfLink`
+    /// instead of panicking if you try to link to yourself.
+    pub fn try_link_line(&self, other: Line, mode: LinkMode) -> Result<(), LinkError> {
         if self.device_id() == other.device_id() {
-            panic!("Do not link to yourself.");
+            return Err(LinkError::SelfLink);
         }
+        let did = other.device_id();
         if mode.monitor() {
             other.plugboard.plug(self.clone(), LinkError::LinkDown)?;
+            self.plugboard.monitor(other.clone(), LinkError::DeviceDown)?;
         }
         if mode.notify() {
-            self.plugboard.plug(other, LinkError::DeviceDown)?;
+            other.plugboard.monitor(self.clone(), LinkError::LinkDown)?;
+            self.plugboard.plug(other.clone(), LinkError::DeviceDown)?;
         }
+        events::linked(self.device_id(), did, mode);
         Ok(())
     }
 
-    /// Links with another Line.
-    pub fn unlink_line(&self, other: &Line, mode: LinkMode) {
-        #[allow(unused_must_use)]
-        if self.device_id() != other.device_id() {
-            if mode.monitor() {
-                other
-                    .plugboard
-                    .unplug(self.device_id(), LinkError::LinkDown);
-            }
-            if mode.notify() {
-                self.plugboard
-                    .unplug(other.device_id(), LinkError::DeviceDown);
-            }
+    /// Unlinks with another Line. Fails with `LinkError::DeviceDown`
+    /// or `LinkError::LinkDown` if either Device has already
+    /// disconnected, and `LinkError::SelfLink` if they are the same.
+    ///
+    /// Lines only queue changes for their Devices to pick up, so
+    /// unlike `Device::unlink_line()`, this can't tell whether a link
+    /// actually existed.
+    pub fn unlink_line(&self, other: &Line, mode: LinkMode) -> Result<(), LinkError> {
+        if self.device_id() == other.device_id() {
+            return Err(LinkError::SelfLink);
+        }
+        let mut ret = Ok(());
+        let mut check = |r: Result<(), LinkError>| {
+            if let (Ok(()), Err(e)) = (&ret, r) { ret = Err(e); }
+        };
+        if mode.monitor() {
+            check(other.plugboard.unplug(self.device_id(), LinkError::LinkDown));
+            check(self.plugboard.unmonitor(other.device_id(), LinkError::DeviceDown));
+        }
+        if mode.notify() {
+            check(self.plugboard.unplug(other.device_id(), LinkError::DeviceDown));
+            check(other.plugboard.unmonitor(self.device_id(), LinkError::LinkDown));
         }
+        events::unlinked(self.device_id(), other.device_id(), mode);
+        ret
     }
 }
 
@@ -1,11 +1,14 @@
 use crate::{DeviceID, Line};
+use alloc::vec::Vec;
 
-pub type Drain<'a, T> = std::vec::Drain<'a, T>;
+pub type Drain<'a, T> = alloc::vec::Drain<'a, T>;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub(crate) enum LineOp {
     Attach(Line),
     Detach(DeviceID),
+    Monitor(Line),
+    Unmonitor(DeviceID),
 }
 
 #[derive(Debug)]
@@ -31,17 +34,6 @@ impl LineMap {
         }
     }
 
-    pub fn apply(&mut self, op: LineOp) {
-        match op {
-            LineOp::Attach(line) => {
-                self.attach(line);
-            }
-            LineOp::Detach(did) => {
-                self.detach(did);
-            }
-        }
-    }
-
     /// Returns whether the line was found and overwritten.
     pub fn attach(&mut self, line: Line) -> bool {
         match self.inner {
@@ -49,6 +41,14 @@ impl LineMap {
         }
     }
 
+    /// Attaches many lines in a single pass, overwriting any we
+    /// already have. Returns how many were overwritten.
+    pub fn attach_all(&mut self, lines: Vec<Line>) -> usize {
+        match self.inner {
+            Inner::Small(ref mut small) => small.attach_all(lines),
+        }
+    }
+
     /// Returns whether the item was found and deleted.
     pub fn detach(&mut self, did: DeviceID) -> bool {
         match self.inner {
@@ -56,7 +56,18 @@ impl LineMap {
         }
     }
 
-    pub fn drain(&mut self) -> Drain<(DeviceID, Option<Line>)> {
+    /// Returns whether there is a line for the Device.
+    pub fn contains(&self, did: DeviceID) -> bool {
+        self.iter().any(|line| line.device_id() == did)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Line> {
+        match self.inner {
+            Inner::Small(ref small) => small.inner.iter().filter_map(|(_, l)| l.as_ref()),
+        }
+    }
+
+    pub fn drain(&mut self) -> Drain<'_, (DeviceID, Option<Line>)> {
         match self.inner {
             Inner::Small(ref mut small) => small.inner.drain(..),
         }
@@ -84,6 +95,36 @@ impl Small {
         false
     }
 
+    fn attach_all(&mut self, lines: Vec<Line>) -> usize {
+        // Sorted, so we can look each existing entry up as we pass.
+        let mut lines: Vec<(DeviceID, Option<Line>)> =
+            lines.into_iter().map(|l| (l.device_id(), Some(l))).collect();
+        lines.sort_by_key(|(did, _)| *did);
+        lines.dedup_by_key(|(did, _)| *did);
+        let mut free: Vec<usize> = Vec::new();
+        let mut overwritten = 0;
+        for (idx, (did, ref mut loon)) in self.inner.iter_mut().enumerate() {
+            if let Ok(found) = lines.binary_search_by_key(did, |(d, _)| *d) {
+                if let Some(line) = lines[found].1.take() {
+                    *loon = Some(line);
+                    overwritten += 1;
+                }
+            } else if loon.is_none() {
+                free.push(idx);
+            }
+        }
+        let new = lines.len() - overwritten;
+        self.inner.reserve(new.saturating_sub(free.len()));
+        for (did, line) in lines.into_iter().filter(|(_, l)| l.is_some()) {
+            if let Some(idx) = free.pop() {
+                self.inner[idx] = (did, line);
+            } else {
+                self.inner.push((did, line));
+            }
+        }
+        overwritten
+    }
+
     fn detach(&mut self, did: DeviceID) -> bool {
         let count = self.inner.len();
         for (idx, (did2, ref mut loon)) in self.inner.iter_mut().enumerate() {
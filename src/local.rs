@@ -0,0 +1,622 @@
+use crate::events;
+use crate::*;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use futures_core::Stream;
+use core::future::poll_fn;
+#[cfg(feature = "std")]
+use {
+    crate::managed,
+    core::fmt::Debug,
+    core::time::Duration,
+    futures_lite::future::pending,
+    futures_lite::{pin, Future},
+};
+
+/// A Device for futures which never leave the thread they were
+/// created on. Nothing about it is thread-safe, which makes it
+/// cheaper than a `Device`, but it can't be sent anywhere.
+///
+/// LocalDevices link with each other directly or through
+/// `LocalLine`s. A thread-safe Device can't hold a LocalLine, so to
+/// link with one, use `link_shared()` or hand it a `shared_line()`.
+/// Either sets up a bridge through which thread-safe Devices see us
+/// under our own DeviceID.
+#[derive(Debug)]
+pub struct LocalDevice {
+    board: Rc<Board>,
+}
+
+/// A reference to a `LocalDevice` that allows us to link with it.
+#[derive(Clone)]
+pub struct LocalLine {
+    board: Rc<Board>,
+}
+
+#[derive(Debug)]
+struct Board {
+    state: RefCell<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    // Devices we will notify when we disconnect.
+    out: Vec<LocalLine>,
+    // Devices which will notify us when they disconnect.
+    ins: Vec<LocalLine>,
+    messages: VecDeque<Message>,
+    waker: Option<Waker>,
+    // Tasks waiting for us to become ready or disconnect.
+    watchers: Vec<Waker>,
+    closed: bool,
+    fault: Option<Fault>,
+    ready: bool,
+    name: Option<String>,
+    // Carries messages to and from thread-safe Devices.
+    bridge: Option<Device>,
+}
+
+impl Board {
+    // Our address, which doesn't move as we live in an Rc.
+    fn device_id(&self) -> DeviceID {
+        DeviceID::new(self as *const Board as usize)
+    }
+
+    fn send(&self, message: Message) -> Result<(), SendError> {
+        let mut state = self.state.borrow_mut();
+        let ret = if state.closed {
+            Err(SendError::Closed(message))
+        } else {
+            state.messages.push_back(message);
+            Ok(())
+        };
+        let waker = state.waker.take();
+        drop(state);
+        events::sent(self.device_id(), &message, ret.is_ok());
+        if let Some(waker) = waker { waker.wake(); }
+        ret
+    }
+
+    fn is_open(&self) -> bool {
+        !self.state.borrow().closed
+    }
+
+    fn wake_watchers(&self) {
+        let watchers = mem::take(&mut self.state.borrow_mut().watchers);
+        for waker in watchers {
+            waker.wake();
+        }
+    }
+
+    fn poll_disconnected(&self, ctx: &mut Context) -> Poll<Option<Fault>> {
+        let mut state = self.state.borrow_mut();
+        if state.closed {
+            Poll::Ready(state.fault)
+        } else {
+            watch(&mut state.watchers, ctx);
+            Poll::Pending
+        }
+    }
+
+    fn poll_ready(&self, ctx: &mut Context) -> Poll<Result<(), ReadyError>> {
+        let mut state = self.state.borrow_mut();
+        if state.ready {
+            Poll::Ready(Ok(()))
+        } else if state.closed {
+            Poll::Ready(Err(ReadyError::Disconnected(state.fault)))
+        } else {
+            watch(&mut state.watchers, ctx);
+            Poll::Pending
+        }
+    }
+}
+
+// Registers the task once, however often it polls.
+fn watch(watchers: &mut Vec<Waker>, ctx: &Context) {
+    if !watchers.iter().any(|w| w.will_wake(ctx.waker())) {
+        watchers.push(ctx.waker().clone());
+    }
+}
+
+impl Drop for Board {
+    fn drop(&mut self) {
+        // Nobody will be receiving what's left.
+        events::dequeued(self.state.get_mut().messages.len());
+    }
+}
+
+fn attach(lines: &mut Vec<LocalLine>, line: LocalLine) {
+    if !lines.contains(&line) {
+        lines.push(line);
+    }
+}
+
+// Returns whether the Device was found and removed.
+fn detach(lines: &mut Vec<LocalLine>, did: DeviceID) -> bool {
+    let count = lines.len();
+    lines.retain(|l| l.device_id() != did);
+    lines.len() != count
+}
+
+// Stop tracking a Device once we hear it has disconnected, so we
+// don't keep its Board alive.
+fn received(state: &mut State, message: &Message) {
+    if let Disconnected(did, _) = message {
+        detach(&mut state.ins, *did);
+        detach(&mut state.out, *did);
+    }
+}
+
+fn link_mode(state: &State, did: DeviceID) -> Option<LinkMode> {
+    let has = |lines: &Vec<LocalLine>| lines.iter().any(|l| l.device_id() == did);
+    match (has(&state.ins), has(&state.out)) {
+        (true, true) => Some(LinkMode::Peer),
+        (true, false) => Some(LinkMode::Monitor),
+        (false, true) => Some(LinkMode::Notify),
+        (false, false) => None,
+    }
+}
+
+impl Default for LocalDevice {
+    fn default() -> Self { LocalDevice::new() }
+}
+
+impl LocalDevice {
+    /// Creates a new LocalDevice.
+    pub fn new() -> Self {
+        let board = Board { state: RefCell::new(State::default()) };
+        let device = LocalDevice { board: Rc::new(board) };
+        events::created(device.device_id());
+        device
+    }
+
+    /// Get the ID of this Device.
+    pub fn device_id(&self) -> DeviceID {
+        self.board.device_id()
+    }
+
+    /// Opens a line to the Device.
+    pub fn line(&self) -> LocalLine {
+        LocalLine { board: self.board.clone() }
+    }
+
+    /// Opens a line thread-safe Devices can use to link with us.
+    pub fn shared_line(&self) -> Line {
+        self.with_bridge(|bridge| bridge.line())
+    }
+
+    /// Gives the Device a name for diagnostic purposes.
+    pub fn set_name(&self, name: &str) {
+        self.board.state.borrow_mut().name = Some(name.to_string());
+        events::named(self.device_id(), name);
+    }
+
+    /// The name given with `set_name()`, if any.
+    pub fn name(&self) -> Option<String> {
+        self.board.state.borrow().name.clone()
+    }
+
+    /// Announce that we have finished initialising to anyone waiting
+    /// on `LocalLine::ready()` or `Line::ready()`.
+    pub fn ready(&self) {
+        let mut state = self.board.state.borrow_mut();
+        state.ready = true;
+        if let Some(bridge) = &state.bridge { bridge.ready(); }
+        drop(state);
+        self.board.wake_watchers();
+    }
+
+    /// Notify our peers we're disconnecting.
+    pub fn disconnect(self, fault: Option<Fault>) {
+        self.do_disconnect(fault);
+    }
+
+    fn do_disconnect(&self, fault: Option<Fault>) {
+        let did = self.device_id();
+        events::disconnected(did, fault);
+        let mut state = self.board.state.borrow_mut();
+        state.closed = true;
+        state.fault = fault;
+        state.ins.clear();
+        let out = mem::take(&mut state.out);
+        let bridge = state.bridge.take();
+        drop(state);
+        self.board.wake_watchers();
+        #[allow(unused_must_use)]
+        for line in out {
+            line.board.send(Disconnected(did, fault));
+        }
+        if let Some(bridge) = bridge {
+            bridge.shut(fault);
+        }
+    }
+
+    // Calls the function with our bridge, making it if need be.
+    fn with_bridge<F: FnOnce(&Device) -> T, T>(&self, f: F) -> T {
+        let mut state = self.board.state.borrow_mut();
+        if state.bridge.is_none() {
+            let bridge = Device::bridge(self.board.device_id());
+            if state.ready { bridge.ready(); }
+            state.bridge = Some(bridge);
+        }
+        f(state.bridge.as_ref().unwrap())
+    }
+
+    /// Link with another LocalDevice with the provided LinkMode.
+    /// LinkModes are additive, so you can 'upgrade' a link this way.
+    ///
+    /// Panics if you try to link to yourself. See `try_link()`.
+    pub fn link(&self, other: &LocalDevice, mode: LinkMode) {
+        if self.try_link(other, mode).is_err() {
+            panic!("Do not link to yourself.");
+        }
+    }
+
+    /// Like `link()`, but fails with `LinkError::SelfLink` instead
+    /// of panicking if you try to link to yourself.
+    pub fn try_link(&self, other: &LocalDevice, mode: LinkMode) -> Result<(), LinkError> {
+        match self.line().try_link_line(other.line(), mode) {
+            Err(LinkError::SelfLink) => Err(LinkError::SelfLink),
+            _ => Ok(()),
+        }
+    }
+
+    /// Unlink from another LocalDevice with the provided
+    /// LinkMode. LinkModes are subtractive, so you can 'downgrade' a
+    /// link this way.
+    pub fn unlink(&self, other: &LocalDevice, mode: LinkMode) {
+        #[allow(unused_must_use)]
+        {
+            self.unlink_line(&other.line(), mode);
+        }
+    }
+
+    /// Link with a line.
+    pub fn link_line(&self, other: LocalLine, mode: LinkMode) -> Result<(), LinkError> {
+        self.line().link_line(other, mode)
+    }
+
+    /// Unlink with a line, as `LocalLine::unlink_line()` would.
+    pub fn unlink_line(&self, other: &LocalLine, mode: LinkMode) -> Result<(), LinkError> {
+        self.line().unlink_line(other, mode)
+    }
+
+    /// Link with a thread-safe Device through its Line, as
+    /// `Device::link_line()` would.
+    pub fn link_shared(&self, other: Line, mode: LinkMode) -> Result<(), LinkError> {
+        self.with_bridge(|bridge| bridge.link_line(other, mode))
+    }
+
+    /// Unlink with a thread-safe Device's Line, as
+    /// `Device::unlink_line()` would.
+    pub fn unlink_shared(&self, other: &Line, mode: LinkMode) -> Result<(), LinkError> {
+        self.with_bridge(|bridge| bridge.unlink_line(other, mode))
+    }
+
+    // Stop notifying a Device which has disconnected.
+    #[cfg(feature = "std")]
+    fn forget(&self, did: DeviceID) {
+        let mut state = self.board.state.borrow_mut();
+        detach(&mut state.out, did);
+        detach(&mut state.ins, did);
+        if let Some(bridge) = &state.bridge { bridge.forget(did); }
+    }
+
+    /// Lists the Devices we are linked to and how, including
+    /// thread-safe ones. Devices which have already disconnected are
+    /// omitted.
+    pub fn links(&self) -> Vec<(DeviceID, LinkMode)> {
+        let state = self.board.state.borrow();
+        let mut links: Vec<(DeviceID, LinkMode)> = Vec::new();
+        let lines = state.ins.iter().chain(state.out.iter());
+        for line in lines.filter(|l| l.is_alive()) {
+            let did = line.device_id();
+            if let Some(mode) = link_mode(&state, did) {
+                if !links.iter().any(|(d, _)| *d == did) {
+                    links.push((did, mode));
+                }
+            }
+        }
+        if let Some(bridge) = &state.bridge {
+            links.extend(bridge.links());
+        }
+        links
+    }
+
+    /// Returns how we are linked to the LocalDevice on the other end
+    /// of the line, if at all.
+    pub fn link_mode(&self, other: &LocalLine) -> Option<LinkMode> {
+        link_mode(&self.board.state.borrow(), other.device_id())
+    }
+
+    /// Attempts to get the next message. Does not wait for one to arrive.
+    pub fn receive(&self) -> Option<Message> {
+        let mut state = self.board.state.borrow_mut();
+        if let Some(message) = state.messages.pop_front() {
+            events::dequeued(1);
+            received(&mut state, &message);
+            return Some(message);
+        }
+        state.bridge.as_ref().and_then(|b| b.receive())
+    }
+}
+
+// Supervision needs to catch panics, which needs std.
+#[cfg(feature = "std")]
+impl LocalDevice {
+    /// Like `Device::watch()`.
+    pub async fn watch<F, C>(&mut self, f: F) -> Result<Watched<<F as Future>::Output>, Crash<C>>
+    where
+        F: Future + Unpin,
+        F::Output: Debug,
+        C: 'static + Debug,
+    {
+        managed::watch(self, f).await
+    }
+
+    /// Like `Device::watch_timeout()`.
+    pub async fn watch_timeout<F, C, T>(
+        &mut self,
+        f: F,
+        timer: &T,
+        timeout: Duration,
+    ) -> Result<Watched<<F as Future>::Output>, Crash<C>>
+    where
+        F: Future + Unpin,
+        F::Output: Debug,
+        C: 'static + Debug,
+        T: Timer,
+    {
+        let delay = timer.delay(timeout);
+        pin!(delay);
+        managed::watch_until(self, f, delay).await
+    }
+
+    /// Like `Device::part_manage()`.
+    pub async fn part_manage<F, T, C>(self, f: F) -> Result<(LocalDevice, T), Crash<C>>
+    where
+        F: Future<Output = Result<T, C>> + Unpin,
+        C: 'static + Debug,
+        T: Debug,
+    {
+        managed::part_manage_until(self, f, pending()).await
+    }
+
+    /// Like `Device::part_manage_timeout()`.
+    pub async fn part_manage_timeout<F, T, C, R>(
+        self,
+        f: F,
+        timer: &R,
+        timeout: Duration,
+    ) -> Result<(LocalDevice, T), Crash<C>>
+    where
+        F: Future<Output = Result<T, C>> + Unpin,
+        C: 'static + Debug,
+        T: Debug,
+        R: Timer,
+    {
+        let delay = timer.delay(timeout);
+        pin!(delay);
+        managed::part_manage_until(self, f, delay).await
+    }
+
+    /// Like `Device::manage()`.
+    pub async fn manage<F, C, T>(self, f: F) -> Result<T, Crash<C>>
+    where
+        F: Future<Output = Result<T, C>> + Unpin,
+        C: 'static + Debug,
+        T: Debug,
+    {
+        managed::manage_until(self, f, pending()).await
+    }
+
+    /// Like `Device::manage_timeout()`.
+    pub async fn manage_timeout<F, C, T, R>(
+        self,
+        f: F,
+        timer: &R,
+        timeout: Duration,
+    ) -> Result<T, Crash<C>>
+    where
+        F: Future<Output = Result<T, C>> + Unpin,
+        C: 'static + Debug,
+        T: Debug,
+        R: Timer,
+    {
+        let delay = timer.delay(timeout);
+        pin!(delay);
+        managed::manage_until(self, f, delay).await
+    }
+}
+
+#[cfg(feature = "std")]
+impl managed::Managed for LocalDevice {
+    type Line = LocalLine;
+
+    fn line(&self) -> LocalLine { LocalDevice::line(self) }
+
+    fn device_id(&self) -> DeviceID { LocalDevice::device_id(self) }
+
+    fn disconnect(self, fault: Option<Fault>) { LocalDevice::disconnect(self, fault) }
+
+    fn forget(&self, did: DeviceID) { LocalDevice::forget(self, did) }
+
+    fn crashed<C: Debug>(line: &LocalLine, crash: &Crash<C>) {
+        events::crashed(line.device_id(), line.name(), crash);
+    }
+}
+
+impl Drop for LocalDevice {
+    fn drop(&mut self) {
+        if self.board.is_open() {
+            self.do_disconnect(Some(Fault::Drop));
+        }
+    }
+}
+
+impl Unpin for LocalDevice {}
+
+impl Stream for LocalDevice {
+    type Item = Message;
+    fn poll_next(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Option<Self::Item>> {
+        let mut state = self.board.state.borrow_mut();
+        if let Some(message) = state.messages.pop_front() {
+            events::dequeued(1);
+            received(&mut state, &message);
+            return Poll::Ready(Some(message));
+        }
+        if let Some(bridge) = state.bridge.as_mut() {
+            if let Poll::Ready(Some(message)) = Pin::new(bridge).poll_next(ctx) {
+                return Poll::Ready(Some(message));
+            }
+        }
+        state.waker = Some(ctx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl LocalLine {
+    /// Get the ID of the Device this line is connected to.
+    pub fn device_id(&self) -> DeviceID {
+        self.board.device_id()
+    }
+
+    /// The name the Device was given with `LocalDevice::set_name()`, if any.
+    pub fn name(&self) -> Option<String> {
+        self.board.state.borrow().name.clone()
+    }
+
+    /// true if the Device has not yet disconnected.
+    pub fn is_alive(&self) -> bool {
+        self.board.is_open()
+    }
+
+    /// Waits for the Device to disconnect, returning the fault it
+    /// disconnected with, if any. Does not require linking.
+    pub async fn disconnected(&self) -> Option<Fault> {
+        poll_fn(|ctx| self.board.poll_disconnected(ctx)).await
+    }
+
+    /// true if the Device has announced it is ready.
+    pub fn is_ready(&self) -> bool {
+        self.board.state.borrow().ready
+    }
+
+    /// Waits for the Device to announce it is ready with
+    /// `LocalDevice::ready()`. Fails if it disconnects first.
+    pub async fn ready(&self) -> Result<(), ReadyError> {
+        poll_fn(|ctx| self.board.poll_ready(ctx)).await
+    }
+
+    /// Send a message to the Device. Returns the original message on
+    /// failure (if the Device has disconnected).
+    pub fn send(&self, message: Message) -> Result<(), Message> {
+        self.board.send(message).map_err(SendError::into_message)
+    }
+
+    /// Like `send()`, but says why we failed.
+    pub fn try_send(&self, message: Message) -> Result<(), SendError> {
+        self.board.send(message)
+    }
+
+    /// Sends a message to every line, returning how many Devices it
+    /// was delivered to.
+    pub fn broadcast<'a, I>(lines: I, message: Message) -> usize
+    where
+        I: IntoIterator<Item = &'a LocalLine>,
+    {
+        lines.into_iter().filter(|line| line.board.send(message).is_ok()).count()
+    }
+
+    /// Links with a Device through its line. Panics if you try to
+    /// link to yourself.
+    pub fn link_line(&self, other: LocalLine, mode: LinkMode) -> Result<(), LinkError> {
+        match self.try_link_line(other, mode) {
+            Err(LinkError::SelfLink) => panic!("Do not link to yourself."),
+            ret => ret,
+        }
+    }
+
+    /// Like `link_line()`, but fails with `LinkError::SelfLink`
+    /// instead of panicking if you try to link to yourself.
+    pub fn try_link_line(&self, other: LocalLine, mode: LinkMode) -> Result<(), LinkError> {
+        if self.device_id() == other.device_id() {
+            return Err(LinkError::SelfLink);
+        }
+        if !self.is_alive() {
+            return Err(LinkError::DeviceDown);
+        }
+        if !other.is_alive() {
+            return Err(LinkError::LinkDown);
+        }
+        let did = other.device_id();
+        let mut ours = self.board.state.borrow_mut();
+        let mut theirs = other.board.state.borrow_mut();
+        if mode.monitor() {
+            attach(&mut theirs.out, self.clone());
+            attach(&mut ours.ins, other.clone());
+        }
+        if mode.notify() {
+            attach(&mut ours.out, other.clone());
+            attach(&mut theirs.ins, self.clone());
+        }
+        drop((ours, theirs));
+        events::linked(self.device_id(), did, mode);
+        Ok(())
+    }
+
+    /// Unlinks with another line. Fails with `LinkError::DeviceDown`
+    /// or `LinkError::LinkDown` if either Device has already
+    /// disconnected, `LinkError::NotLinked` if they weren't linked in
+    /// the given mode and `LinkError::SelfLink` if they are the same.
+    pub fn unlink_line(&self, other: &LocalLine, mode: LinkMode) -> Result<(), LinkError> {
+        let (us, them) = (self.device_id(), other.device_id());
+        if us == them {
+            return Err(LinkError::SelfLink);
+        }
+        let mut found = false;
+        let mut ours = self.board.state.borrow_mut();
+        let mut theirs = other.board.state.borrow_mut();
+        if mode.monitor() {
+            found |= detach(&mut theirs.out, us);
+            found |= detach(&mut ours.ins, them);
+        }
+        if mode.notify() {
+            found |= detach(&mut ours.out, them);
+            found |= detach(&mut theirs.ins, us);
+        }
+        let (closed, their_closed) = (ours.closed, theirs.closed);
+        drop((ours, theirs));
+        events::unlinked(us, them, mode);
+        if closed {
+            Err(LinkError::DeviceDown)
+        } else if their_closed {
+            Err(LinkError::LinkDown)
+        } else if !found {
+            Err(LinkError::NotLinked)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Eq for LocalLine {}
+
+impl PartialEq for LocalLine {
+    fn eq(&self, other: &LocalLine) -> bool {
+        Rc::ptr_eq(&self.board, &other.board)
+    }
+}
+
+impl fmt::Debug for LocalLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("LocalLine<{:x}>", self.device_id()))
+    }
+}
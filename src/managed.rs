@@ -0,0 +1,134 @@
+// Supervision, shared by Device and LocalDevice. Each gets a thin
+// public wrapper documenting what these do.
+use crate::panic::dont_panic;
+use crate::Watched::{Completed, Messaged};
+use crate::*;
+use core::fmt::Debug;
+use futures_lite::{Future, FutureExt, Stream, StreamExt};
+
+// What supervision needs from a Device.
+pub(crate) trait Managed: Stream<Item = Message> + Unpin + Sized {
+    // Something to remember the Device by once it is gone.
+    type Line;
+    fn line(&self) -> Self::Line;
+    fn device_id(&self) -> DeviceID;
+    fn disconnect(self, fault: Option<Fault>);
+    fn forget(&self, did: DeviceID);
+    fn crashed<C: Debug>(line: &Self::Line, crash: &Crash<C>);
+}
+
+pub(crate) async fn watch<D, F, C>(device: &mut D, f: F) -> Result<Watched<F::Output>, Crash<C>>
+where
+    D: Managed,
+    F: Future + Unpin,
+    F::Output: Debug,
+    C: 'static + Debug,
+{
+    let fut = dont_panic(f);
+    async {
+        let message = device.next().await.expect("The Device to still be usable.");
+        Ok(Messaged(message))
+    }
+    .or(async {
+        match fut.await {
+            Ok(val) => Ok(Completed(val)),
+            Err(unwind) => Err(Crash::Panic(unwind)),
+        }
+    })
+    .await
+}
+
+// Like `watch()`, but crashes with a timeout when `deadline` completes.
+pub(crate) async fn watch_until<D, F, C, T>(
+    device: &mut D,
+    f: F,
+    deadline: T,
+) -> Result<Watched<F::Output>, Crash<C>>
+where
+    D: Managed,
+    F: Future + Unpin,
+    F::Output: Debug,
+    C: 'static + Debug,
+    T: Future<Output = ()> + Unpin,
+{
+    watch(device, f)
+        .or(async {
+            deadline.await;
+            Err(Crash::Timeout)
+        })
+        .await
+}
+
+// Like `part_manage()`, but times out when `deadline` completes.
+pub(crate) async fn part_manage_until<D, F, T, C, R>(
+    mut device: D,
+    mut f: F,
+    mut deadline: R,
+) -> Result<(D, T), Crash<C>>
+where
+    D: Managed,
+    F: Future<Output = Result<T, C>> + Unpin,
+    C: 'static + Debug,
+    T: Debug,
+    R: Future<Output = ()> + Unpin,
+{
+    let line = device.line();
+    let did = device.device_id();
+    let ret = events::managing(did, async move {
+        loop {
+            match watch_until(&mut device, &mut f, &mut deadline).await {
+                Ok(Completed(Ok(val))) => {
+                    return Ok((device, val));
+                }
+                Ok(Completed(Err(val))) => {
+                    device.disconnect(Some(Fault::Error));
+                    return Err(Crash::Error(val));
+                }
+                Ok(Messaged(Disconnected(sender, Some(fault)))) => {
+                    device.disconnect(Some(Fault::cascade(sender, fault)));
+                    return Err(Crash::Cascade(sender, fault));
+                }
+                Ok(Messaged(Disconnected(sender, None))) => {
+                    device.forget(sender);
+                    continue;
+                }
+                Ok(Messaged(Tick(_))) => continue,
+                Ok(Messaged(Shutdown(id))) => {
+                    device.disconnect(None);
+                    return Err(Crash::PowerOff(id));
+                }
+                Err(Crash::Timeout) => {
+                    device.disconnect(Some(Fault::Timeout));
+                    return Err(Crash::Timeout);
+                }
+                Err(crash) => {
+                    device.disconnect(Some(Fault::Error));
+                    return Err(crash);
+                }
+            }
+        }
+    })
+    .await;
+    if let Err(crash) = &ret {
+        D::crashed(&line, crash);
+    }
+    ret
+}
+
+// Like `part_manage_until()`, but disconnects on success too.
+pub(crate) async fn manage_until<D, F, T, C, R>(device: D, f: F, deadline: R) -> Result<T, Crash<C>>
+where
+    D: Managed,
+    F: Future<Output = Result<T, C>> + Unpin,
+    C: 'static + Debug,
+    T: Debug,
+    R: Future<Output = ()> + Unpin,
+{
+    match part_manage_until(device, f, deadline).await {
+        Ok((device, val)) => {
+            device.disconnect(None);
+            Ok(val)
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,137 @@
+//! Process-wide counters for Devices, faults and messages.
+//!
+//! Counters are plain atomics updated as things happen. Take a
+//! `snapshot()` to read them, and render it with `to_prometheus()`
+//! if that's what you're scraping with.
+use crate::*;
+use alloc::string::String;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+static CLEAN: AtomicUsize = AtomicUsize::new(0);
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+static ERRORED: AtomicUsize = AtomicUsize::new(0);
+static TIMED_OUT: AtomicUsize = AtomicUsize::new(0);
+static LOST: AtomicUsize = AtomicUsize::new(0);
+static NODE_DOWN: AtomicUsize = AtomicUsize::new(0);
+static CASCADED: AtomicUsize = AtomicUsize::new(0);
+static SENT: AtomicUsize = AtomicUsize::new(0);
+static FAILED: AtomicUsize = AtomicUsize::new(0);
+// Signed as a message may be received before its sender counts it.
+static QUEUED: AtomicIsize = AtomicIsize::new(0);
+
+/// The counters at a point in time.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Metrics {
+    /// Devices created.
+    pub created: usize,
+    /// Devices not yet disconnected.
+    pub live: usize,
+    /// Devices which disconnected without a fault.
+    pub disconnected_clean: usize,
+    /// Devices which disconnected with `Fault::Drop`.
+    pub disconnected_drop: usize,
+    /// Devices which disconnected with `Fault::Error`.
+    pub disconnected_error: usize,
+    /// Devices which disconnected with `Fault::Timeout`.
+    pub disconnected_timeout: usize,
+    /// Devices which disconnected with `Fault::ConnectionLost`.
+    pub disconnected_connection_lost: usize,
+    /// Devices which disconnected with `Fault::NodeDown`.
+    pub disconnected_node_down: usize,
+    /// Devices which disconnected with `Fault::Cascade`.
+    pub cascades: usize,
+    /// Messages delivered to a mailbox.
+    pub messages_sent: usize,
+    /// Messages which could not be delivered.
+    pub messages_failed: usize,
+    /// Messages waiting in mailboxes.
+    pub mailbox_depth: usize,
+}
+
+/// Reads the counters.
+pub fn snapshot() -> Metrics {
+    let mut m = Metrics {
+        created: CREATED.load(Ordering::Relaxed),
+        live: 0,
+        disconnected_clean: CLEAN.load(Ordering::Relaxed),
+        disconnected_drop: DROPPED.load(Ordering::Relaxed),
+        disconnected_error: ERRORED.load(Ordering::Relaxed),
+        disconnected_timeout: TIMED_OUT.load(Ordering::Relaxed),
+        disconnected_connection_lost: LOST.load(Ordering::Relaxed),
+        disconnected_node_down: NODE_DOWN.load(Ordering::Relaxed),
+        cascades: CASCADED.load(Ordering::Relaxed),
+        messages_sent: SENT.load(Ordering::Relaxed),
+        messages_failed: FAILED.load(Ordering::Relaxed),
+        mailbox_depth: QUEUED.load(Ordering::Relaxed).max(0) as usize,
+    };
+    let gone = m.disconnected_clean + m.disconnected_drop + m.disconnected_error
+        + m.disconnected_timeout + m.disconnected_connection_lost
+        + m.disconnected_node_down + m.cascades;
+    m.live = m.created.saturating_sub(gone);
+    m
+}
+
+impl Metrics {
+    /// Renders the counters in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, values: &[(&str, usize)]| {
+            writeln!(out, "# HELP backplane_{} {}", name, help).unwrap();
+            writeln!(out, "# TYPE backplane_{} {}", name, kind).unwrap();
+            for (labels, value) in values {
+                writeln!(out, "backplane_{}{} {}", name, labels, value).unwrap();
+            }
+        };
+        metric("devices_created_total", "counter", "Devices created.", &[("", self.created)]);
+        metric("devices_live", "gauge", "Devices not yet disconnected.", &[("", self.live)]);
+        metric("disconnects_total", "counter", "Devices disconnected, by fault.", &[
+            ("{fault=\"none\"}", self.disconnected_clean),
+            ("{fault=\"drop\"}", self.disconnected_drop),
+            ("{fault=\"error\"}", self.disconnected_error),
+            ("{fault=\"timeout\"}", self.disconnected_timeout),
+            ("{fault=\"connection_lost\"}", self.disconnected_connection_lost),
+            ("{fault=\"node_down\"}", self.disconnected_node_down),
+            ("{fault=\"cascade\"}", self.cascades),
+        ]);
+        metric("messages_sent_total", "counter", "Messages delivered to a mailbox.", &[("", self.messages_sent)]);
+        metric("messages_failed_total", "counter", "Messages which could not be delivered.", &[("", self.messages_failed)]);
+        metric("mailbox_depth", "gauge", "Messages waiting in mailboxes.", &[("", self.mailbox_depth)]);
+        out
+    }
+}
+
+#[inline(always)]
+pub(crate) fn created() {
+    CREATED.fetch_add(1, Ordering::Relaxed);
+}
+
+#[inline(always)]
+pub(crate) fn sent(ok: bool) {
+    if ok {
+        SENT.fetch_add(1, Ordering::Relaxed);
+        QUEUED.fetch_add(1, Ordering::Relaxed);
+    } else {
+        FAILED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[inline(always)]
+pub(crate) fn dequeued(count: usize) {
+    QUEUED.fetch_sub(count as isize, Ordering::Relaxed);
+}
+
+#[inline(always)]
+pub(crate) fn disconnected(fault: Option<Fault>) {
+    let counter = match fault {
+        None => &CLEAN,
+        Some(Fault::Drop) => &DROPPED,
+        Some(Fault::Error) => &ERRORED,
+        Some(Fault::Timeout) => &TIMED_OUT,
+        Some(Fault::ConnectionLost) => &LOST,
+        Some(Fault::NodeDown) => &NODE_DOWN,
+        Some(Fault::Cascade(_, _)) => &CASCADED,
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
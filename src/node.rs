@@ -0,0 +1,756 @@
+//! Linking Devices across processes.
+//!
+//! Each process runs a `Node` with an ID unique among the nodes it
+//! connects to. A Node exports Lines under names, and a `Connection`
+//! to another Node can look them up, returning a `RemoteLine` which
+//! links like a local `Line` does. DeviceIDs of remote Devices carry
+//! the ID of the node they live on.
+//!
+//! When a connection is lost, every Device monitoring a Device
+//! across it is sent `Disconnected(_, Some(Fault::ConnectionLost))`.
+//! Both sides tick several times per tick time, and a Node which has
+//! not been heard from for a whole tick time is declared down with
+//! `Fault::NodeDown`, so a hung process is noticed too.
+//!
+//! Connections do their I/O on their own threads, so nothing but
+//! `Connection::lookup()` waits on the network and they work with any
+//! executor. Async code should use `Connection::lookup_async()`. They run over TCP or, on Linux, Unix sockets.
+//! Unix connections check the credentials of the process on the
+//! other end, by default only accepting processes run by our user.
+use crate::wire::{read_frame, write_frame, Frame};
+use crate::*;
+use atomic_waker::AtomicWaker;
+use futures_lite::future::{block_on, poll_fn};
+use futures_lite::StreamExt;
+use std::collections::hash_map::{Entry, HashMap};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown as NetShutdown, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(target_os = "linux")]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(target_os = "linux")]
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::Poll;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// This process's presence on the network.
+#[derive(Clone)]
+pub struct Node {
+    inner: Arc<Exports>,
+}
+
+struct Exports {
+    id: u32,
+    lines: Mutex<HashMap<String, Line>>,
+    check: Mutex<Arc<PeerCheck>>,
+    tick_time: Mutex<Duration>,
+}
+
+// How many ticks we send per tick time.
+const TICKS: u32 = 4;
+
+type PeerCheck = dyn Fn(&Credentials) -> bool + Send + Sync;
+
+/// Who is running the process on the other end of a Unix socket.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Credentials {
+    /// Process ID.
+    pub pid: i32,
+    /// User ID.
+    pub uid: u32,
+    /// Group ID.
+    pub gid: u32,
+}
+
+impl Credentials {
+    /// Our own credentials.
+    #[cfg(target_os = "linux")]
+    pub fn current() -> Credentials {
+        use rustix::process::{getgid, getpid, getuid};
+        Credentials {
+            pid: getpid().as_raw_nonzero().get(),
+            uid: getuid().as_raw(),
+            gid: getgid().as_raw(),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn of(stream: &UnixStream) -> io::Result<Credentials> {
+        let cred = rustix::net::sockopt::socket_peercred(stream)?;
+        Ok(Credentials {
+            pid: cred.pid.as_raw_nonzero().get(),
+            uid: cred.uid.as_raw(),
+            gid: cred.gid.as_raw(),
+        })
+    }
+}
+
+// A bidirectional byte stream we can run a connection over.
+pub(crate) trait Transport: Read + Write + Send + Sync + Sized + 'static {
+    fn try_clone(&self) -> io::Result<Self>;
+    fn close(&self);
+    fn set_timeouts(&self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()>;
+}
+
+impl Transport for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn close(&self) {
+        #[allow(unused_must_use)]
+        {
+            self.shutdown(NetShutdown::Both);
+        }
+    }
+
+    fn set_timeouts(&self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        self.set_read_timeout(read)?;
+        self.set_write_timeout(write)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Transport for UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        UnixStream::try_clone(self)
+    }
+
+    fn close(&self) {
+        #[allow(unused_must_use)]
+        {
+            self.shutdown(NetShutdown::Both);
+        }
+    }
+
+    fn set_timeouts(&self, read: Option<Duration>, write: Option<Duration>) -> io::Result<()> {
+        self.set_read_timeout(read)?;
+        self.set_write_timeout(write)
+    }
+}
+
+impl Node {
+    /// Creates a Node. Panics if the ID is 0, which means 'this
+    /// process' in a DeviceID.
+    pub fn new(id: u32) -> Node {
+        if id == 0 {
+            panic!("Node IDs must not be 0.");
+        }
+        Node {
+            inner: Arc::new(Exports {
+                id,
+                lines: Mutex::new(HashMap::new()),
+                check: Mutex::new(Arc::new(same_user)),
+                tick_time: Mutex::new(Duration::from_secs(60)),
+            }),
+        }
+    }
+
+    /// Our ID.
+    pub fn id(&self) -> u32 {
+        self.inner.id
+    }
+
+    /// How long a connected Node may go without being heard from
+    /// before it is declared down. Defaults to one minute.
+    pub fn tick_time(&self) -> Duration {
+        *self.inner.tick_time.lock().unwrap()
+    }
+
+    /// Sets the tick time for connections made from now on. Panics if
+    /// it is zero.
+    pub fn set_tick_time(&self, tick_time: Duration) {
+        if tick_time == Duration::from_secs(0) {
+            panic!("The tick time must not be zero.");
+        }
+        *self.inner.tick_time.lock().unwrap() = tick_time;
+    }
+
+    /// Allows connected Nodes to look the Line up by name, replacing
+    /// any Line previously exported under it.
+    pub fn export(&self, name: &str, line: Line) {
+        self.inner.lines.lock().unwrap().insert(name.to_string(), line);
+    }
+
+    /// Stops exporting the Line under the name. Connections which
+    /// already looked it up may continue to use it.
+    pub fn unexport(&self, name: &str) -> Option<Line> {
+        self.inner.lines.lock().unwrap().remove(name)
+    }
+
+    /// Connects to a Node listening on TCP.
+    pub fn connect<A: ToSocketAddrs>(&self, addr: A) -> io::Result<Connection> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        self.attach(stream, None)
+    }
+
+    /// Accepts the next connection from a Node on the listener.
+    pub fn accept(&self, listener: &TcpListener) -> io::Result<Connection> {
+        let (stream, _) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        self.attach(stream, None)
+    }
+
+    /// Decides which processes we will talk to over Unix sockets. The
+    /// default accepts processes run by the same user as us.
+    pub fn set_peer_check<F>(&self, check: F)
+    where F: Fn(&Credentials) -> bool + Send + Sync + 'static {
+        *self.inner.check.lock().unwrap() = Arc::new(check);
+    }
+
+    /// Connects to a Node listening on a Unix socket.
+    #[cfg(target_os = "linux")]
+    pub fn connect_unix<P: AsRef<Path>>(&self, path: P) -> io::Result<Connection> {
+        self.attach_unix(UnixStream::connect(path)?)
+    }
+
+    /// Accepts the next connection from a Node on the Unix listener.
+    #[cfg(target_os = "linux")]
+    pub fn accept_unix(&self, listener: &UnixListener) -> io::Result<Connection> {
+        let (stream, _) = listener.accept()?;
+        self.attach_unix(stream)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn attach_unix(&self, stream: UnixStream) -> io::Result<Connection> {
+        let peer = Credentials::of(&stream)?;
+        let check = self.inner.check.lock().unwrap().clone();
+        if !check(&peer) {
+            stream.close();
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Peer not allowed"));
+        }
+        self.attach(stream, Some(peer))
+    }
+
+    // Introduces ourselves and starts running the connection.
+    pub(crate) fn attach<S: Transport>(&self, stream: S, peer: Option<Credentials>) -> io::Result<Connection> {
+        let mut reader = stream.try_clone()?;
+        let mut writer = stream.try_clone()?;
+        let here = self.id();
+        let tick_time = self.tick_time();
+        // A hung peer shouldn't hang us, even while we say hello.
+        stream.set_timeouts(Some(tick_time), Some(tick_time))?;
+        write_frame(&mut writer, &Frame::Hello { node: here }, here)?;
+        let remote = match read_frame(&mut reader, here)? {
+            Frame::Hello { node } if node != 0 && node != here => node,
+            _ => {
+                stream.close();
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Bad handshake"));
+            }
+        };
+        stream.set_timeouts(None, Some(tick_time))?;
+        let port = Device::new();
+        port.set_name(&format!("node {} port", remote));
+        let (outbox, frames) = mpsc::channel();
+        let conn = Arc::new(Conn {
+            exports: self.inner.clone(),
+            remote,
+            peer,
+            outbox: Mutex::new(Some(outbox)),
+            closer: Box::new(move || stream.close()),
+            port: port.line(),
+            state: Mutex::new(State::default()),
+            next_id: AtomicU64::new(0),
+            tick_time,
+            heard: Mutex::new(Instant::now()),
+        });
+        let c = conn.clone();
+        thread::spawn(move || c.run_port(port));
+        let c = conn.clone();
+        thread::spawn(move || c.run_reader(reader));
+        let c = conn.clone();
+        thread::spawn(move || c.run_writer(writer, frames));
+        let c = conn.clone();
+        thread::spawn(move || c.run_ticker());
+        Ok(Connection { inner: conn })
+    }
+}
+
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("Node<{}>", self.id()))
+    }
+}
+
+/// A connection to another Node. Cloning gives another handle to the
+/// same connection, which stays open until closed or lost.
+#[derive(Clone)]
+pub struct Connection {
+    inner: Arc<Conn>,
+}
+
+struct Conn {
+    exports: Arc<Exports>,
+    remote: u32,
+    peer: Option<Credentials>,
+    // Frames for the writer thread, taken when the connection is lost.
+    outbox: Mutex<Option<mpsc::Sender<Frame>>>,
+    closer: Box<dyn Fn() + Send + Sync>,
+    // Monitors our Devices on behalf of the other side.
+    port: Line,
+    state: Mutex<State>,
+    next_id: AtomicU64,
+    tick_time: Duration,
+    // When we last received a frame.
+    heard: Mutex<Instant>,
+}
+
+#[derive(Default)]
+struct State {
+    lost: bool,
+    // Their Devices -> our Devices monitoring them.
+    imports: HashMap<DeviceID, Vec<Line>>,
+    // Our Devices -> their Devices monitoring them.
+    exports: HashMap<DeviceID, Export>,
+    // Our Devices they have looked up.
+    known: HashMap<DeviceID, Line>,
+    // How big `known` may grow before we sweep out the disconnected.
+    sweep_at: usize,
+    lookups: HashMap<u64, Arc<Answer>>,
+}
+
+// Where the other side's answer to a lookup goes.
+#[derive(Default)]
+struct Answer {
+    device: Mutex<Option<Option<DeviceID>>>,
+    waker: AtomicWaker,
+}
+
+// One of our Devices monitored from the other side.
+struct Export {
+    watchers: Vec<DeviceID>,
+    line: Line,
+}
+
+impl Connection {
+    /// The ID of the Node on the other end.
+    pub fn remote_node(&self) -> u32 {
+        self.inner.remote
+    }
+
+    /// The credentials of the process on the other end, if this is
+    /// a Unix socket connection.
+    pub fn peer_credentials(&self) -> Option<Credentials> {
+        self.inner.peer
+    }
+
+    /// false once the connection has been closed or lost.
+    pub fn is_alive(&self) -> bool {
+        !self.inner.state.lock().unwrap().lost
+    }
+
+    /// Asks the other Node for the Line it exported under the name.
+    /// Blocks until it answers, so don't call it from async code.
+    pub fn lookup(&self, name: &str) -> io::Result<Option<RemoteLine>> {
+        block_on(self.lookup_async(name))
+    }
+
+    /// Like `lookup()`, but waits for the answer asynchronously.
+    pub async fn lookup_async(&self, name: &str) -> io::Result<Option<RemoteLine>> {
+        let answer = Arc::new(Answer::default());
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut state = self.inner.state.lock().unwrap();
+            if state.lost {
+                return Err(lost());
+            }
+            state.lookups.insert(id, answer.clone());
+        }
+        self.inner.send(Frame::Lookup { id, name: name.to_string() })?;
+        let device = poll_fn(|ctx| {
+            answer.waker.register(ctx.waker());
+            let device = *answer.device.lock().unwrap();
+            if let Some(device) = device {
+                Poll::Ready(Ok(device))
+            } else if self.inner.state.lock().unwrap().lost {
+                Poll::Ready(Err(lost()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await?;
+        Ok(device.map(|device_id| RemoteLine { device_id, conn: self.inner.clone() }))
+    }
+
+    /// Closes the connection. Devices monitoring Devices across it
+    /// are notified with `Fault::ConnectionLost`.
+    pub fn close(&self) {
+        (self.inner.closer)();
+        self.inner.lost(Fault::ConnectionLost);
+    }
+}
+
+impl fmt::Debug for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("Connection<{}>", self.inner.remote))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn same_user(peer: &Credentials) -> bool {
+    peer.uid == Credentials::current().uid
+}
+
+// Without Unix sockets there is nobody to check.
+#[cfg(not(target_os = "linux"))]
+fn same_user(_peer: &Credentials) -> bool {
+    false
+}
+
+fn lost() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "Connection lost")
+}
+
+impl Conn {
+    fn here(&self) -> u32 {
+        self.exports.id
+    }
+
+    // Queues a frame for the writer thread.
+    fn send(&self, frame: Frame) -> io::Result<()> {
+        match self.outbox.lock().unwrap().as_ref() {
+            Some(outbox) => outbox.send(frame).map_err(|_| lost()),
+            None => Err(lost()),
+        }
+    }
+
+    fn run_writer<W: Write>(self: Arc<Self>, mut writer: W, frames: mpsc::Receiver<Frame>) {
+        for frame in frames {
+            if write_frame(&mut writer, &frame, self.here()).is_err() {
+                break;
+            }
+        }
+        (self.closer)();
+        self.lost(Fault::ConnectionLost);
+    }
+
+    // Tells the other side about our Devices disconnecting.
+    fn run_port(self: Arc<Self>, mut port: Device) {
+        while let Some(message) = block_on(port.next()) {
+            match message {
+                Disconnected(did, fault) => {
+                    let mut frames = Vec::new();
+                    {
+                        let mut state = self.state.lock().unwrap();
+                        state.known.remove(&did);
+                        if state.exports.remove(&did).is_some() {
+                            frames.push(Frame::Disconnected { device: did, fault });
+                        }
+                        // It may have been watching their Devices too.
+                        state.imports.retain(|target, lines| {
+                            let count = lines.len();
+                            lines.retain(|l| l.device_id() != did);
+                            if lines.len() != count {
+                                frames.push(Frame::Unwatch { watcher: did, target: *target });
+                            }
+                            !lines.is_empty()
+                        });
+                    }
+                    for frame in frames {
+                        #[allow(unused_must_use)]
+                        {
+                            self.send(frame);
+                        }
+                    }
+                }
+                Shutdown(_) => break,
+                Tick(_) => {}
+            }
+        }
+        port.disconnect(None);
+    }
+
+    fn run_reader<R: Read>(self: Arc<Self>, mut reader: R) {
+        while let Ok(frame) = read_frame(&mut reader, self.here()) {
+            *self.heard.lock().unwrap() = Instant::now();
+            if let Some(reply) = self.handle(frame) {
+                if self.send(reply).is_err() { break; }
+            }
+        }
+        (self.closer)();
+        self.lost(Fault::ConnectionLost);
+    }
+
+    // Lets the other side know we're alive and checks they are.
+    fn run_ticker(self: Arc<Self>) {
+        loop {
+            thread::sleep(self.tick_time / TICKS);
+            if self.state.lock().unwrap().lost { return; }
+            if self.heard.lock().unwrap().elapsed() > self.tick_time {
+                // Before closing, or the reader would call it lost.
+                self.lost(Fault::NodeDown);
+                (self.closer)();
+                return;
+            }
+            #[allow(unused_must_use)]
+            {
+                self.send(Frame::Tick);
+            }
+        }
+    }
+
+    // Acts on a frame from the other side, returning any reply.
+    #[allow(unused_must_use)]
+    fn handle(&self, frame: Frame) -> Option<Frame> {
+        let mut state = self.state.lock().unwrap();
+        match frame {
+            Frame::Lookup { id, name } => {
+                let line = self.exports.lines.lock().unwrap().get(&name).cloned();
+                let device = line.map(|line| {
+                    let did = line.device_id();
+                    state.remember(did, line);
+                    did
+                });
+                return Some(Frame::Found { id, device });
+            }
+            Frame::Found { id, device } => {
+                if let Some(answer) = state.lookups.remove(&id) {
+                    *answer.device.lock().unwrap() = Some(device);
+                    answer.waker.wake();
+                }
+            }
+            Frame::Watch { watcher, target } => {
+                let line = match state.known.get(&target) {
+                    Some(line) => line.clone(),
+                    None => return Some(Frame::Disconnected { device: target, fault: None }),
+                };
+                if state.export(&self.port, &line, watcher).is_err() {
+                    state.known.remove(&target);
+                    let fault = *line.plugboard.fault.lock();
+                    return Some(Frame::Disconnected { device: target, fault });
+                }
+            }
+            Frame::Unwatch { watcher, target } => { state.unexport(&self.port, target, watcher); }
+            Frame::Watching { watcher, target } => {
+                let imported = match state.known.get(&watcher).cloned() {
+                    Some(line) => state.import(&self.port, target, line).is_ok(),
+                    None => false,
+                };
+                // Our Device is gone, so they needn't tell it anything.
+                if !imported {
+                    return Some(Frame::Unwatch { watcher, target });
+                }
+            }
+            Frame::Unwatching { watcher, target } => { state.unimport(&self.port, target, watcher); }
+            Frame::Disconnected { device, fault } => {
+                for line in state.imports.remove(&device).unwrap_or_default() {
+                    line.plugboard.send(Disconnected(device, fault));
+                    state.unplug(&self.port, &line);
+                }
+            }
+            Frame::Message { to, message } => {
+                if let Some(line) = state.known.get(&to) {
+                    line.plugboard.send(message);
+                }
+            }
+            Frame::Hello { .. } | Frame::Tick => {}
+        }
+        None
+    }
+
+    // Notifies everyone monitoring a Device across the connection.
+    #[allow(unused_must_use)]
+    fn lost(&self, fault: Fault) {
+        let mut state = self.state.lock().unwrap();
+        if state.lost { return; }
+        state.lost = true;
+        // Lets the writer thread finish once it has written the rest.
+        self.outbox.lock().unwrap().take();
+        for (_, answer) in state.lookups.drain() {
+            answer.waker.wake();
+        }
+        for (did, lines) in state.imports.drain() {
+            for line in lines {
+                line.plugboard.send(Disconnected(did, Some(fault)));
+                line.plugboard.unplug(self.port.device_id(), LinkError::LinkDown);
+            }
+        }
+        for (_, export) in state.exports.drain() {
+            export.line.plugboard.unplug(self.port.device_id(), LinkError::LinkDown);
+        }
+        state.known.clear();
+        self.port.plugboard.send(Shutdown(self.port.device_id()));
+    }
+}
+
+impl State {
+    // Remembers one of our Devices they looked up, every so often
+    // forgetting those which have since disconnected.
+    fn remember(&mut self, did: DeviceID, line: Line) {
+        self.known.insert(did, line);
+        if self.known.len() > self.sweep_at {
+            self.known.retain(|_, line| line.is_alive());
+            self.sweep_at = 2 * self.known.len().max(16);
+        }
+    }
+
+    // Records that their Device monitors one of ours, plugging the
+    // port into ours if nobody else did. Returns whether it is new.
+    fn export(&mut self, port: &Line, line: &Line, watcher: DeviceID) -> Result<bool, LinkError> {
+        let export = match self.exports.entry(line.device_id()) {
+            Entry::Occupied(export) => export.into_mut(),
+            Entry::Vacant(vacant) => {
+                line.plugboard.plug(port.clone(), LinkError::LinkDown)?;
+                vacant.insert(Export { watchers: Vec::new(), line: line.clone() })
+            }
+        };
+        if export.watchers.contains(&watcher) {
+            return Ok(false);
+        }
+        export.watchers.push(watcher);
+        Ok(true)
+    }
+
+    // Returns whether the watcher was found and removed.
+    fn unexport(&mut self, port: &Line, target: DeviceID, watcher: DeviceID) -> bool {
+        let export = match self.exports.get_mut(&target) {
+            Some(export) => export,
+            None => return false,
+        };
+        let count = export.watchers.len();
+        export.watchers.retain(|w| *w != watcher);
+        let found = export.watchers.len() != count;
+        if export.watchers.is_empty() {
+            let export = self.exports.remove(&target).unwrap();
+            self.unplug(port, &export.line);
+        }
+        found
+    }
+
+    // Records that one of our Devices monitors theirs, plugging the
+    // port into ours so we can tell them if it goes first. Returns
+    // whether it is new.
+    fn import(&mut self, port: &Line, target: DeviceID, line: Line) -> Result<bool, LinkError> {
+        if matches!(self.imports.get(&target), Some(lines) if lines.contains(&line)) {
+            return Ok(false);
+        }
+        line.plugboard.plug(port.clone(), LinkError::LinkDown)?;
+        self.imports.entry(target).or_default().push(line);
+        Ok(true)
+    }
+
+    // Returns whether the watcher was found and removed.
+    fn unimport(&mut self, port: &Line, target: DeviceID, watcher: DeviceID) -> bool {
+        let watchers = match self.imports.get_mut(&target) {
+            Some(watchers) => watchers,
+            None => return false,
+        };
+        let idx = match watchers.iter().position(|l| l.device_id() == watcher) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        let line = watchers.remove(idx);
+        if watchers.is_empty() {
+            self.imports.remove(&target);
+        }
+        self.unplug(port, &line);
+        true
+    }
+
+    // Unplugs the port from one of our Devices once we no longer
+    // need to hear about it.
+    fn unplug(&self, port: &Line, line: &Line) {
+        let did = line.device_id();
+        let importing = self.imports.values().any(|lines| lines.contains(line));
+        if !importing && !self.exports.contains_key(&did) {
+            #[allow(unused_must_use)]
+            {
+                line.plugboard.unplug(port.device_id(), LinkError::LinkDown);
+            }
+        }
+    }
+}
+
+/// A Line to a Device on another Node.
+#[derive(Clone)]
+pub struct RemoteLine {
+    device_id: DeviceID,
+    conn: Arc<Conn>,
+}
+
+impl RemoteLine {
+    /// Get the ID of the Device this line is connected to.
+    pub fn device_id(&self) -> DeviceID {
+        self.device_id
+    }
+
+    /// Send a message to the Device. Returns the original message on
+    /// failure (if the connection has been lost).
+    pub fn send(&self, message: Message) -> Result<(), Message> {
+        if self.conn.state.lock().unwrap().lost {
+            return Err(message);
+        }
+        self.conn.send(Frame::Message { to: self.device_id, message }).map_err(|_| message)
+    }
+
+    /// Links the remote Device with a local one, just like
+    /// `Line::link_line()`.
+    pub fn link_line(&self, other: Line, mode: LinkMode) -> Result<(), LinkError> {
+        let did = other.device_id();
+        let mut frames = Vec::new();
+        {
+            let mut state = self.conn.state.lock().unwrap();
+            if state.lost {
+                return Err(LinkError::DeviceDown);
+            }
+            // Linking again in a mode we already have changes nothing.
+            if mode.monitor() && state.export(&self.conn.port, &other, self.device_id)? {
+                frames.push(Frame::Watching { watcher: self.device_id, target: did });
+            }
+            if mode.notify() && state.import(&self.conn.port, self.device_id, other)? {
+                frames.push(Frame::Watch { watcher: did, target: self.device_id });
+            }
+        }
+        for frame in frames {
+            self.conn.send(frame).map_err(|_| LinkError::DeviceDown)?;
+        }
+        events::linked(self.device_id, did, mode);
+        Ok(())
+    }
+
+    /// Unlinks the remote Device from a local one, just like
+    /// `Device::unlink_line()`. Fails with `LinkError::DeviceDown`,
+    /// changing nothing, if the connection has been lost, or with
+    /// `LinkError::NotLinked` if there was no link to remove.
+    pub fn unlink_line(&self, other: &Line, mode: LinkMode) -> Result<(), LinkError> {
+        let did = other.device_id();
+        let mut state = self.conn.state.lock().unwrap();
+        if state.lost {
+            return Err(LinkError::DeviceDown);
+        }
+        let unwatching = mode.monitor()
+            && matches!(state.exports.get(&did), Some(export) if export.watchers.contains(&self.device_id));
+        let unwatch = mode.notify()
+            && matches!(state.imports.get(&self.device_id), Some(lines) if lines.contains(other));
+        let mut frames = Vec::new();
+        if unwatching {
+            frames.push(Frame::Unwatching { watcher: self.device_id, target: did });
+        }
+        if unwatch {
+            frames.push(Frame::Unwatch { watcher: did, target: self.device_id });
+        }
+        // Only forget the link once the other side will too.
+        for frame in frames {
+            self.conn.send(frame).map_err(|_| LinkError::DeviceDown)?;
+        }
+        if unwatching {
+            state.unexport(&self.conn.port, did, self.device_id);
+        }
+        if unwatch {
+            state.unimport(&self.conn.port, self.device_id, did);
+        }
+        drop(state);
+        events::unlinked(self.device_id, did, mode);
+        if unwatching || unwatch { Ok(()) } else { Err(LinkError::NotLinked) }
+    }
+}
+
+impl fmt::Debug for RemoteLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("RemoteLine<{:x}>", self.device_id))
+    }
+}
@@ -0,0 +1,59 @@
+//! Process-wide hooks for reacting to the lifecycle of every Device.
+//!
+//! Install an `Observer` to feed dashboards, crash reporters and the
+//! like. When none is installed, the cost is a single atomic load.
+use crate::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Hears about the lifecycle of every Device in the process. All
+/// methods do nothing by default.
+///
+/// Observers are called synchronously from whatever thread caused
+/// the event, so keep them quick.
+#[allow(unused_variables)]
+pub trait Observer: Send + Sync {
+    /// A Device was created.
+    fn created(&self, device: DeviceID) {}
+
+    /// A link was added between two Devices.
+    fn linked(&self, device: DeviceID, other: DeviceID, mode: LinkMode) {}
+
+    /// A link was removed between two Devices.
+    fn unlinked(&self, device: DeviceID, other: DeviceID, mode: LinkMode) {}
+
+    /// A message could not be sent because the Device has disconnected.
+    fn send_failed(&self, device: DeviceID, message: &Message) {}
+
+    /// A Device disconnected.
+    fn disconnected(&self, device: DeviceID, fault: Option<Fault>) {}
+}
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static OBSERVER: RwLock<Option<Arc<dyn Observer>>> = RwLock::new(None);
+
+/// Installs an observer, replacing any previously installed.
+pub fn install(observer: Arc<dyn Observer>) {
+    let mut slot = OBSERVER.write().unwrap();
+    *slot = Some(observer);
+    INSTALLED.store(true, Ordering::Release);
+}
+
+/// Removes the installed observer, if any, returning it.
+pub fn uninstall() -> Option<Arc<dyn Observer>> {
+    let mut slot = OBSERVER.write().unwrap();
+    INSTALLED.store(false, Ordering::Release);
+    slot.take()
+}
+
+// Calls the installed observer, if there is one. We don't hold the
+// lock while calling it in case it wants to (un)install.
+#[inline(always)]
+pub(crate) fn notify<F: FnOnce(&dyn Observer)>(f: F) {
+    if INSTALLED.load(Ordering::Acquire) {
+        let observer = OBSERVER.read().unwrap().clone();
+        if let Some(observer) = observer {
+            f(&*observer);
+        }
+    }
+}
@@ -1,19 +1,70 @@
+use crate::events;
 use crate::linemap::LineOp;
-use crate::{DeviceID, Line, LinkError, Message};
-use concurrent_queue::ConcurrentQueue;
-use waker_queue::WakerQueue;
+use crate::{DeviceID, Fault, Line, LinkError, Message, ReadyError, SendError};
+use atomic_waker::AtomicWaker;
+use concurrent_queue::{ConcurrentQueue, PopError, PushError};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::mem;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+
+#[cfg(all(not(feature = "std"), feature = "spin"))]
+use spin::Mutex;
+
+// std's Mutex where we have it, so waiting threads sleep rather
+// than spin. A panic can't leave the data half-written, so we ignore
+// poisoning as spin would.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct Mutex<T>(std::sync::Mutex<T>);
+
+#[cfg(feature = "std")]
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        Mutex(std::sync::Mutex::new(value))
+    }
+
+    pub fn lock(&self) -> std::sync::MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
 
 #[derive(Debug)]
 pub(crate) struct Plugboard {
     pub line_ops: ConcurrentQueue<LineOp>,
-    pub messages: WakerQueue<Message>,
+    pub messages: ConcurrentQueue<Message>,
+    // The Device waiting for a message.
+    pub waker: AtomicWaker,
+    // Tasks waiting for us to become ready or disconnect.
+    pub watchers: Mutex<Vec<Waker>>,
+    // Written once, just before we close.
+    pub fault: Mutex<Option<Fault>>,
+    pub ready: AtomicBool,
+    // Boxed, so an unnamed Device pays only for a pointer.
+    pub name: Mutex<Option<Box<str>>>,
+    // The ID of the LocalDevice we bridge for, if we do.
+    pub alias: Option<DeviceID>,
 }
 
 impl Plugboard {
-    pub fn new() -> Self {
+    pub fn new(alias: Option<DeviceID>) -> Self {
         Plugboard {
             line_ops: ConcurrentQueue::unbounded(),
-            messages: WakerQueue::unbounded(),
+            messages: ConcurrentQueue::unbounded(),
+            waker: AtomicWaker::new(),
+            watchers: Mutex::new(Vec::new()),
+            fault: Mutex::new(None),
+            ready: AtomicBool::new(false),
+            name: Mutex::new(None),
+            alias,
+        }
+    }
+
+    pub fn device_id(&self) -> DeviceID {
+        match self.alias {
+            Some(alias) => alias,
+            None => DeviceID::new(self as *const _ as usize),
         }
     }
 
@@ -27,16 +78,115 @@ impl Plugboard {
         self.line_ops.push(LineOp::Detach(did)).map_err(|_| error)
     }
 
+    // Record that this Device will notify us when it disconnects.
+    pub fn monitor(&self, line: Line, error: LinkError) -> Result<(), LinkError> {
+        self.line_ops.push(LineOp::Monitor(line)).map_err(|_| error)
+    }
+
+    // Record that this Device will no longer notify us when it disconnects.
+    pub fn unmonitor(&self, did: DeviceID, error: LinkError) -> Result<(), LinkError> {
+        self.line_ops.push(LineOp::Unmonitor(did)).map_err(|_| error)
+    }
+
     // Send a message down the line.
     pub fn send(&self, message: Message) -> Result<(), Message> {
-        self.messages
-            .try_push_wake(message, true)
-            .map_err(|e| e.into_inner())
+        self.try_send(message).map_err(SendError::into_message)
+    }
+
+    // Send a message down the line, saying why we failed.
+    pub fn try_send(&self, message: Message) -> Result<(), SendError> {
+        let ret = self.messages
+            .push(message)
+            .map_err(|e| match e {
+                PushError::Full(m) => SendError::Full(m),
+                PushError::Closed(m) => SendError::Closed(m),
+            });
+        if ret.is_ok() {
+            self.waker.wake();
+        }
+        events::sent(self.device_id(), &message, ret.is_ok());
+        ret
     }
 
-    // Stop taking requests
-    pub fn close(&self) {
+    // Take the next message from the mailbox.
+    pub fn receive(&self) -> Result<Message, PopError> {
+        let ret = self.messages.pop();
+        if ret.is_ok() {
+            events::dequeued(1);
+        }
+        ret
+    }
+
+    // Stop taking requests and wake anyone waiting for us to go.
+    pub fn close(&self, fault: Option<Fault>) {
+        *self.fault.lock() = fault;
         self.line_ops.close();
         self.messages.close();
+        self.wake_watchers();
+    }
+
+    fn wake_watchers(&self) {
+        let watchers = mem::take(&mut *self.watchers.lock());
+        for waker in watchers {
+            waker.wake();
+        }
+    }
+
+    // Registers the task to be woken when we become ready or
+    // disconnect, once however often it polls. Returns false without
+    // registering if we are no longer `waiting`.
+    fn watch(&self, ctx: &mut Context, waiting: impl Fn() -> bool) -> bool {
+        let mut watchers = self.watchers.lock();
+        // Checked under the lock so we can't miss a wake.
+        if !waiting() {
+            return false;
+        }
+        if !watchers.iter().any(|w| w.will_wake(ctx.waker())) {
+            watchers.push(ctx.waker().clone());
+        }
+        true
+    }
+
+    // Announce we have finished initialising.
+    pub fn set_ready(&self) {
+        if !self.ready.swap(true, Ordering::AcqRel) {
+            self.wake_watchers();
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    // true if we have not yet begun disconnecting.
+    pub fn is_open(&self) -> bool {
+        !self.line_ops.is_closed()
+    }
+
+    // Resolves with our fault once we have disconnected.
+    pub fn poll_disconnected(&self, ctx: &mut Context) -> Poll<Option<Fault>> {
+        if self.watch(ctx, || self.is_open()) {
+            return Poll::Pending;
+        }
+        Poll::Ready(*self.fault.lock())
+    }
+
+    // Resolves once we are ready or have disconnected without being so.
+    pub fn poll_ready(&self, ctx: &mut Context) -> Poll<Result<(), ReadyError>> {
+        if self.watch(ctx, || !self.is_ready() && self.is_open()) {
+            return Poll::Pending;
+        }
+        if self.is_ready() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Ready(Err(ReadyError::Disconnected(*self.fault.lock())))
+        }
+    }
+}
+
+impl Drop for Plugboard {
+    fn drop(&mut self) {
+        // Nobody will be receiving what's left.
+        events::dequeued(self.messages.len());
     }
 }
@@ -1,6 +1,7 @@
 //! Reexports of most things!
+#[cfg(feature = "std")]
 pub use crate::panic::{chain_panic_hook, replace_panic_hook};
 pub use crate::LinkMode::{Monitor, Notify, Peer};
-pub use crate::Message::{Disconnected, Shutdown};
+pub use crate::Message::{Disconnected, Shutdown, Tick};
 pub use crate::Watched::{Completed, Messaged};
 pub use crate::*;
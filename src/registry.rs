@@ -0,0 +1,213 @@
+//! An opt-in, process-wide registry of live Devices and their links.
+//!
+//! Once enabled, every Device created, linked, unlinked or named is
+//! recorded until it disconnects. Take a `snapshot()` to see the
+//! graph and export it to Graphviz DOT or JSON for diffing.
+//!
+//! Devices created before the registry was enabled only appear once
+//! they are linked or named.
+use crate::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static GRAPH: Mutex<Graph> = Mutex::new(Graph {
+    devices: BTreeMap::new(),
+    links: BTreeSet::new(),
+    neighbours: BTreeMap::new(),
+});
+
+// Device -> name, (notifier, notified) pairs and, so a disconnect
+// need not scan every link, Device -> those it has links with.
+struct Graph {
+    devices: BTreeMap<DeviceID, Option<String>>,
+    links: BTreeSet<(DeviceID, DeviceID)>,
+    neighbours: BTreeMap<DeviceID, BTreeSet<DeviceID>>,
+}
+
+impl Graph {
+    fn link(&mut self, from: DeviceID, to: DeviceID) {
+        self.links.insert((from, to));
+        self.neighbours.entry(from).or_default().insert(to);
+        self.neighbours.entry(to).or_default().insert(from);
+    }
+
+    fn unlink(&mut self, from: DeviceID, to: DeviceID) {
+        self.links.remove(&(from, to));
+        if !self.links.contains(&(to, from)) {
+            self.forget_neighbour(from, to);
+            self.forget_neighbour(to, from);
+        }
+    }
+
+    fn forget_neighbour(&mut self, did: DeviceID, other: DeviceID) {
+        if let Some(set) = self.neighbours.get_mut(&did) {
+            set.remove(&other);
+            if set.is_empty() {
+                self.neighbours.remove(&did);
+            }
+        }
+    }
+}
+
+/// Starts recording Devices.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// Stops recording Devices and forgets those already recorded.
+pub fn disable() {
+    ENABLED.store(false, Ordering::Release);
+    let mut graph = GRAPH.lock().unwrap();
+    graph.devices.clear();
+    graph.links.clear();
+    graph.neighbours.clear();
+}
+
+/// true if the registry is recording Devices.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Takes a copy of the graph as it stands.
+pub fn snapshot() -> Snapshot {
+    let graph = GRAPH.lock().unwrap();
+    Snapshot {
+        devices: graph.devices.iter()
+            .map(|(did, name)| Node { device_id: *did, name: name.clone() })
+            .collect(),
+        links: graph.links.iter()
+            .map(|(from, to)| Edge { from: *from, to: *to })
+            .collect(),
+    }
+}
+
+/// A live Device.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Node {
+    /// The Device's ID.
+    pub device_id: DeviceID,
+    /// As given to `Device::set_name()`.
+    pub name: Option<String>,
+}
+
+/// `from` will notify `to` when it disconnects.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Edge {
+    /// The Device which will send the notification.
+    pub from: DeviceID,
+    /// The Device which will receive it.
+    pub to: DeviceID,
+}
+
+/// The live Devices and their links at a point in time, ordered by
+/// DeviceID.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Snapshot {
+    /// Every live Device.
+    pub devices: Vec<Node>,
+    /// Every link between live Devices.
+    pub links: Vec<Edge>,
+}
+
+impl Snapshot {
+    /// Renders the graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph backplane {\n");
+        for node in self.devices.iter() {
+            let label = node.name.clone().unwrap_or_else(|| node.device_id.to_string());
+            writeln!(out, "  \"{:x}\" [label=\"{}\"];", node.device_id, dot_escape(&label)).unwrap();
+        }
+        for edge in self.links.iter() {
+            writeln!(out, "  \"{:x}\" -> \"{:x}\";", edge.from, edge.to).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the graph as JSON: `{"devices": [{"id", "name"}],
+    /// "links": [{"from", "to"}]}`, with ids as hex strings.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"devices\":[");
+        for (i, node) in self.devices.iter().enumerate() {
+            if i > 0 { out.push(','); }
+            write!(out, "{{\"id\":\"{:x}\",\"name\":", node.device_id).unwrap();
+            match &node.name {
+                Some(name) => write!(out, "\"{}\"}}", json_escape(name)).unwrap(),
+                None => out.push_str("null}"),
+            }
+        }
+        out.push_str("],\"links\":[");
+        for (i, edge) in self.links.iter().enumerate() {
+            if i > 0 { out.push(','); }
+            write!(out, "{{\"from\":\"{:x}\",\"to\":\"{:x}\"}}", edge.from, edge.to).unwrap();
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Updates the graph if we are enabled.
+#[inline(always)]
+fn record<F: FnOnce(&mut Graph)>(f: F) {
+    if ENABLED.load(Ordering::Acquire) {
+        f(&mut GRAPH.lock().unwrap());
+    }
+}
+
+pub(crate) fn created(did: DeviceID) {
+    record(|g| { g.devices.entry(did).or_insert(None); });
+}
+
+pub(crate) fn named(did: DeviceID, name: &str) {
+    record(|g| { g.devices.insert(did, Some(name.to_string())); });
+}
+
+pub(crate) fn linked(did: DeviceID, other: DeviceID, mode: LinkMode) {
+    record(|g| {
+        g.devices.entry(did).or_insert(None);
+        g.devices.entry(other).or_insert(None);
+        if mode.monitor() { g.link(other, did); }
+        if mode.notify() { g.link(did, other); }
+    });
+}
+
+pub(crate) fn unlinked(did: DeviceID, other: DeviceID, mode: LinkMode) {
+    record(|g| {
+        if mode.monitor() { g.unlink(other, did); }
+        if mode.notify() { g.unlink(did, other); }
+    });
+}
+
+pub(crate) fn disconnected(did: DeviceID) {
+    record(|g| {
+        g.devices.remove(&did);
+        for other in g.neighbours.remove(&did).unwrap_or_default() {
+            g.links.remove(&(did, other));
+            g.links.remove(&(other, did));
+            g.forget_neighbour(other, did);
+        }
+    });
+}
@@ -0,0 +1,142 @@
+//! Structured reports of Devices crashing in managed mode.
+//!
+//! Install a `Sink` and every crash with a fault in `part_manage()`
+//! (and everything built on it) is delivered to it as a
+//! `CrashReport`. Installing a sink also chains a panic hook which
+//! records where panics happened, so you don't need to call
+//! `panic::chain_panic_hook()` yourself.
+use crate::*;
+use maybe_unwind::capture_panic_info;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once, RwLock};
+use std::time::SystemTime;
+
+/// Everything we know about why a Device crashed.
+#[derive(Clone, Debug)]
+pub struct CrashReport {
+    /// The Device which crashed.
+    pub device_id: DeviceID,
+    /// As given to `Device::set_name()`.
+    pub name: Option<String>,
+    /// What we disconnected with.
+    pub fault: Fault,
+    /// The panic message or the Debug output of the error.
+    pub message: Option<String>,
+    /// Where we panicked, as `file:line:column`.
+    pub location: Option<String>,
+    /// Only captured when enabled with `RUST_BACKTRACE`.
+    pub backtrace: Option<String>,
+    /// When the crash was reported.
+    pub timestamp: SystemTime,
+    /// For cascades, the Device we heard the fault from and, if
+    /// different, the one it started with.
+    pub ancestors: Vec<DeviceID>,
+}
+
+/// Receives crash reports. Called synchronously from the thread the
+/// Device crashed on, so keep it quick.
+pub trait Sink: Send + Sync {
+    /// Handles a report of a managed Device crashing with a fault.
+    fn report(&self, report: CrashReport);
+}
+
+impl<F: Fn(CrashReport) + Send + Sync> Sink for F {
+    fn report(&self, report: CrashReport) {
+        self(report)
+    }
+}
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static SINK: RwLock<Option<Arc<dyn Sink>>> = RwLock::new(None);
+static HOOK: Once = Once::new();
+
+thread_local! {
+    static PANICKED: RefCell<Option<Panicked>> = const { RefCell::new(None) };
+}
+
+// What our panic hook found out about the last panic on this thread.
+struct Panicked {
+    location: Option<String>,
+    backtrace: Option<String>,
+}
+
+/// Installs a sink, replacing any previously installed.
+pub fn install(sink: Arc<dyn Sink>) {
+    HOOK.call_once(|| {
+        let old = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            capture_panic_info(info);
+            let backtrace = Backtrace::capture();
+            let panicked = Panicked {
+                location: info.location().map(|l| l.to_string()),
+                backtrace: match backtrace.status() {
+                    BacktraceStatus::Captured => Some(backtrace.to_string()),
+                    _ => None,
+                },
+            };
+            PANICKED.with(|p| *p.borrow_mut() = Some(panicked));
+            old(info);
+        }));
+    });
+    let mut slot = SINK.write().unwrap();
+    *slot = Some(sink);
+    INSTALLED.store(true, Ordering::Release);
+}
+
+/// Removes the installed sink, if any, returning it. The panic hook
+/// stays in place.
+pub fn uninstall() -> Option<Arc<dyn Sink>> {
+    let mut slot = SINK.write().unwrap();
+    INSTALLED.store(false, Ordering::Release);
+    slot.take()
+}
+
+// Reports the crash if there is a sink and it came with a fault.
+pub(crate) fn crashed<C: Debug>(device_id: DeviceID, name: Option<String>, crash: &Crash<C>) {
+    if !INSTALLED.load(Ordering::Acquire) { return; }
+    let sink = SINK.read().unwrap().clone();
+    if let Some(sink) = sink {
+        if let Some(report) = CrashReport::new(device_id, name, crash) {
+            sink.report(report);
+        }
+    }
+}
+
+impl CrashReport {
+    fn new<C: Debug>(device_id: DeviceID, name: Option<String>, crash: &Crash<C>) -> Option<CrashReport> {
+        let mut report = CrashReport {
+            device_id,
+            name,
+            fault: Fault::Error,
+            message: None,
+            location: None,
+            backtrace: None,
+            timestamp: SystemTime::now(),
+            ancestors: Vec::new(),
+        };
+        match crash {
+            Crash::PowerOff(_) => return None,
+            Crash::Panic(unwind) => {
+                report.message = Some(unwind.payload_str().to_string());
+                if let Some(panicked) = PANICKED.with(|p| p.borrow_mut().take()) {
+                    report.location = panicked.location;
+                    report.backtrace = panicked.backtrace;
+                }
+            }
+            Crash::Error(err) => report.message = Some(format!("{:?}", err)),
+            Crash::Cascade(sender, fault) => {
+                report.fault = Fault::cascade(*sender, *fault);
+                report.ancestors.push(*sender);
+                if let Some(origin) = fault.origin() {
+                    report.ancestors.push(origin.device);
+                }
+            }
+            Crash::Timeout => report.fault = Fault::Timeout,
+        }
+        Some(report)
+    }
+}
@@ -0,0 +1,203 @@
+use crate::panic::dont_panic;
+use crate::*;
+use atomic_waker::AtomicWaker;
+use concurrent_queue::ConcurrentQueue;
+use futures_lite::{Future, Stream};
+use futures_micro::poll_fn;
+use std::fmt::Debug;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+pub(crate) type Child = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// A handle for spawning children inside `Device::scope()`.
+///
+/// Children are peered with the scope's Device and will all have
+/// been shut down and finished by the time the scope returns.
+#[derive(Clone)]
+pub struct Scope {
+    inner: Arc<Nursery>,
+}
+
+struct Nursery {
+    parent: Line,
+    spawned: ConcurrentQueue<(Line, Child)>,
+    waker: AtomicWaker,
+}
+
+impl Scope {
+    fn new(parent: Line) -> Scope {
+        Scope {
+            inner: Arc::new(Nursery {
+                parent,
+                spawned: ConcurrentQueue::unbounded(),
+                waker: AtomicWaker::new(),
+            }),
+        }
+    }
+
+    /// Runs the provided future in managed mode on a new Device
+    /// peered with the scope's Device. Returns a Line to the child.
+    ///
+    /// Fails if the scope has already been left.
+    pub fn spawn<F, T, C>(&self, f: F) -> Result<Line, LinkError>
+    where
+        F: Future<Output = Result<T, C>> + Send + 'static,
+        T: Debug + Send + 'static,
+        C: 'static + Debug + Send,
+    {
+        if self.inner.spawned.is_closed() {
+            return Err(LinkError::DeviceDown);
+        }
+        let device = Device::new();
+        let line = device.line();
+        device.link_line(self.inner.parent.clone(), LinkMode::Peer)?;
+        let child = Box::pin(async move {
+            #[allow(unused_must_use)]
+            {
+                device.manage(Box::pin(f)).await;
+            }
+        });
+        if let Err(unspawned) = self.inner.spawned.push((line.clone(), child)) {
+            // The scope was left in the meantime. Unlink before the
+            // child is dropped, or it would crash the parent.
+            #[allow(unused_must_use)]
+            {
+                line.unlink_line(&self.inner.parent, LinkMode::Peer);
+            }
+            drop(unspawned);
+            return Err(LinkError::DeviceDown);
+        }
+        self.inner.waker.wake();
+        Ok(line)
+    }
+
+    /// Returns the ID of the Device the scope belongs to.
+    pub fn device_id(&self) -> DeviceID {
+        self.inner.parent.device_id()
+    }
+}
+
+impl Debug for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Scope<{:x}>", self.device_id().inner))
+    }
+}
+
+// The children we are running.
+#[derive(Default)]
+pub(crate) struct Children {
+    lines: Vec<Line>,
+    running: Vec<Child>,
+}
+
+impl Children {
+    pub fn push(&mut self, line: Line, child: Child) {
+        self.lines.push(line);
+        self.running.push(child);
+    }
+
+    fn adopt(&mut self, scope: &Scope) {
+        while let Ok((line, child)) = scope.inner.spawned.pop() {
+            self.push(line, child);
+        }
+    }
+
+    // Polls every running child, removing the ones which
+    // completed. Returns true if none are left running.
+    fn poll(&mut self, ctx: &mut Context) -> bool {
+        let mut i = 0;
+        while i < self.running.len() {
+            if self.running[i].as_mut().poll(ctx).is_ready() {
+                drop(self.running.swap_remove(i));
+            } else {
+                i += 1;
+            }
+        }
+        self.running.is_empty()
+    }
+
+    fn contains(&self, did: DeviceID) -> bool {
+        self.lines.iter().any(|l| l.device_id() == did)
+    }
+
+    // Waits for the provided future while running the children.
+    pub async fn until<F: Future>(&mut self, f: F) -> F::Output {
+        futures_lite::pin!(f);
+        poll_fn(|ctx| {
+            self.poll(ctx);
+            f.as_mut().poll(ctx)
+        })
+        .await
+    }
+
+    // Waits for all the children to finish.
+    pub async fn finish(&mut self) {
+        poll_fn(|ctx| {
+            if self.poll(ctx) { Poll::Ready(()) } else { Poll::Pending }
+        })
+        .await
+    }
+}
+
+pub(crate) async fn run<F, Fut, T, C>(device: &mut Device, f: F) -> Result<T, Crash<C>>
+where
+    F: FnOnce(Scope) -> Fut,
+    Fut: Future<Output = Result<T, C>>,
+    C: 'static + Debug + Send,
+    T: Debug,
+{
+    let scope = Scope::new(device.line());
+    let mut children = Children::default();
+    let mut body = Box::pin(dont_panic(f(scope.clone())));
+    let ret = poll_fn(|ctx| {
+        // As with `part_manage()`, messages take priority.
+        while let Poll::Ready(message) = Pin::new(&mut *device).poll_next(ctx) {
+            match message.expect("The Device to still be usable.") {
+                Disconnected(sender, None) => device.forget(sender),
+                Disconnected(sender, Some(fault)) => {
+                    return Poll::Ready(Err(Crash::Cascade(sender, fault)));
+                }
+                Shutdown(id) => return Poll::Ready(Err(Crash::PowerOff(id))),
+                Tick(_) => {}
+            }
+        }
+        let ret = match body.as_mut().poll(ctx) {
+            Poll::Ready(Ok(Ok(val))) => Poll::Ready(Ok(val)),
+            Poll::Ready(Ok(Err(err))) => Poll::Ready(Err(Crash::Error(err))),
+            Poll::Ready(Err(unwind)) => Poll::Ready(Err(Crash::Panic(unwind))),
+            Poll::Pending => Poll::Pending,
+        };
+        if ret.is_pending() {
+            scope.inner.waker.register(ctx.waker());
+            children.adopt(&scope);
+            children.poll(ctx);
+        }
+        ret
+    })
+    .await;
+    drop(body);
+    // Nobody else gets in and everybody still here gets told to leave.
+    scope.inner.spawned.close();
+    children.adopt(&scope);
+    let me = device.device_id();
+    #[allow(unused_must_use)]
+    for line in children.lines.iter() {
+        line.plugboard.send(Shutdown(me));
+    }
+    children.finish().await;
+    // Tidy up after the children, leaving anything else for our owner.
+    for line in children.lines.iter() {
+        device.forget(line.device_id());
+    }
+    let others: Vec<Message> = std::iter::from_fn(|| device.receive())
+        .filter(|m| !children.contains(m.sender()))
+        .collect();
+    let line = device.line();
+    #[allow(unused_must_use)]
+    for message in others {
+        line.plugboard.send(message);
+    }
+    ret
+}
@@ -0,0 +1,213 @@
+//! A single-threaded executor with virtual time, for reproducing
+//! supervision bugs deterministically.
+//!
+//! Tasks which are ready to run are picked in an order determined by
+//! the seed. When nothing is ready, time jumps straight to the next
+//! `SimTimer` delay due, so an hour of timeouts takes no time at all.
+use crate::rng::Rng;
+use crate::Timer;
+use futures_lite::Future;
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::Duration;
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+/// The simulation.
+pub struct Sim {
+    clock: Arc<Mutex<Clock>>,
+    woken: Arc<Mutex<Vec<usize>>>,
+    tasks: Vec<Option<(Task, Arc<TaskWaker>)>>,
+    runnable: Vec<usize>,
+    live: usize,
+    rng: Rng,
+}
+
+// Virtual time and the delays waiting on it.
+#[derive(Debug, Default)]
+struct Clock {
+    now: Duration,
+    seq: u64,
+    timers: BTreeMap<(Duration, u64), Waker>,
+}
+
+struct TaskWaker {
+    id: usize,
+    queued: AtomicBool,
+    woken: Arc<Mutex<Vec<usize>>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.queued.swap(true, Ordering::AcqRel) {
+            self.woken.lock().unwrap().push(self.id);
+        }
+    }
+}
+
+impl Sim {
+    /// Creates a simulation. The same seed with the same tasks
+    /// spawned in the same order runs them in the same order.
+    pub fn new(seed: u64) -> Sim {
+        Sim {
+            clock: Arc::new(Mutex::new(Clock::default())),
+            woken: Arc::new(Mutex::new(Vec::new())),
+            tasks: Vec::new(),
+            runnable: Vec::new(),
+            live: 0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// How much virtual time has passed.
+    pub fn now(&self) -> Duration {
+        self.clock.lock().unwrap().now
+    }
+
+    /// A timer running on virtual time.
+    pub fn timer(&self) -> SimTimer {
+        SimTimer { clock: self.clock.clone() }
+    }
+
+    /// Adds a task to the simulation. It will not run until `run()`
+    /// or `run_for()` is called.
+    pub fn spawn<F: Future<Output = ()> + 'static>(&mut self, future: F) {
+        let waker = Arc::new(TaskWaker {
+            id: self.tasks.len(),
+            queued: AtomicBool::new(false),
+            woken: self.woken.clone(),
+        });
+        waker.wake_by_ref();
+        self.tasks.push(Some((Box::pin(future), waker)));
+        self.live += 1;
+    }
+
+    /// Runs until no task can make progress. Returns how many tasks
+    /// are stuck.
+    pub fn run(&mut self) -> usize {
+        self.run_until(None)
+    }
+
+    /// Runs until no task can make progress or the provided amount
+    /// of virtual time has passed. Returns how many tasks have not
+    /// completed.
+    pub fn run_for(&mut self, duration: Duration) -> usize {
+        let limit = self.now() + duration;
+        self.run_until(Some(limit))
+    }
+
+    fn run_until(&mut self, limit: Option<Duration>) -> usize {
+        while self.live > 0 {
+            self.runnable.append(&mut self.woken.lock().unwrap());
+            if self.runnable.is_empty() && !self.advance(limit) {
+                break;
+            }
+            if self.runnable.is_empty() { continue; }
+            let pick = self.rng.below(self.runnable.len());
+            let id = self.runnable.swap_remove(pick);
+            if let Some((task, waker)) = self.tasks[id].as_mut() {
+                waker.queued.store(false, Ordering::Release);
+                let w = Waker::from(waker.clone());
+                if task.as_mut().poll(&mut Context::from_waker(&w)).is_ready() {
+                    self.tasks[id] = None;
+                    self.live -= 1;
+                }
+            }
+        }
+        self.live
+    }
+
+    // Moves time to the next delay due and wakes everything waiting
+    // on it. Returns false if there is nothing to wait for before
+    // the limit.
+    fn advance(&mut self, limit: Option<Duration>) -> bool {
+        let mut clock = self.clock.lock().unwrap();
+        let next = clock.timers.keys().next().map(|(when, _)| *when);
+        let next = match (next, limit) {
+            (Some(next), Some(limit)) if next <= limit => next,
+            (Some(next), None) => next,
+            (_, limit) => {
+                if let Some(limit) = limit { clock.now = limit; }
+                return false;
+            }
+        };
+        clock.now = clock.now.max(next);
+        let now = clock.now;
+        let later = clock.timers.split_off(&(now, u64::MAX));
+        let due = std::mem::replace(&mut clock.timers, later);
+        drop(clock);
+        for (_, waker) in due {
+            waker.wake();
+        }
+        true
+    }
+}
+
+impl std::fmt::Debug for Sim {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sim")
+            .field("now", &self.now())
+            .field("tasks", &self.live)
+            .finish()
+    }
+}
+
+/// A `Timer` whose delays complete in a `Sim`'s virtual time.
+#[derive(Clone, Debug)]
+pub struct SimTimer {
+    clock: Arc<Mutex<Clock>>,
+}
+
+impl Timer for SimTimer {
+    type Delay = SimDelay;
+
+    fn delay(&self, duration: Duration) -> SimDelay {
+        let when = self.clock.lock().unwrap().now + duration;
+        SimDelay { clock: self.clock.clone(), when, key: None }
+    }
+}
+
+/// The future returned by `SimTimer::delay()`.
+#[derive(Debug)]
+pub struct SimDelay {
+    clock: Arc<Mutex<Clock>>,
+    when: Duration,
+    // Where our waker is filed, if it is.
+    key: Option<(Duration, u64)>,
+}
+
+impl Future for SimDelay {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+        let this = self.get_mut();
+        let mut clock = this.clock.lock().unwrap();
+        if clock.now >= this.when {
+            return Poll::Ready(());
+        }
+        let key = match this.key {
+            Some(key) => key,
+            None => {
+                clock.seq += 1;
+                (this.when, clock.seq)
+            }
+        };
+        clock.timers.insert(key, ctx.waker().clone());
+        this.key = Some(key);
+        Poll::Pending
+    }
+}
+
+impl Drop for SimDelay {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.clock.lock().unwrap().timers.remove(&key);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Tools for testing supervision trees by breaking things on purpose.
+//!
+//! * `inject_fault()` and `inject_shutdown()` send a Device the
+//!   messages it would get if a Device it monitors faulted or it was
+//!   asked to stop.
+//! * A `Trigger` makes a wrapped future panic or be dropped on demand.
+//! * `Chaos` does the above to Devices at random, reproducibly from a
+//!   seed.
+use crate::rng::Rng;
+use crate::*;
+use atomic_waker::AtomicWaker;
+use futures_lite::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+
+/// Tells the Device a Device it monitors faulted.
+pub fn inject_fault(line: &Line, sender: DeviceID, fault: Fault) -> Result<(), Message> {
+    line.plugboard.send(Disconnected(sender, Some(fault)))
+}
+
+/// Asks the Device to shut down.
+pub fn inject_shutdown(line: &Line, sender: DeviceID) -> Result<(), Message> {
+    line.plugboard.send(Shutdown(sender))
+}
+
+/// Something a `Trigger` can do to a future.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(usize)]
+pub enum Injection {
+    /// Panic the next time it is polled.
+    Panic = 1,
+    /// Drop it (and any Device it owns), completing with `None`.
+    Drop = 2,
+}
+
+/// Fires an `Injection` at the futures it wrapped.
+#[derive(Clone, Debug, Default)]
+pub struct Trigger {
+    inner: Arc<Armed>,
+}
+
+#[derive(Debug, Default)]
+struct Armed {
+    injection: AtomicUsize,
+    // One per wrapped future, so firing wakes every task.
+    wakers: Mutex<Vec<Weak<AtomicWaker>>>,
+}
+
+impl Trigger {
+    /// Creates a Trigger which has not been fired.
+    pub fn new() -> Trigger {
+        Trigger::default()
+    }
+
+    /// Wraps a future so that it may be sabotaged.
+    pub fn wrap<F: Future>(&self, future: F) -> Faulty<F> {
+        let waker = Arc::new(AtomicWaker::new());
+        let mut wakers = self.inner.wakers.lock().unwrap();
+        wakers.retain(|w| w.strong_count() > 0); // forget the dropped
+        wakers.push(Arc::downgrade(&waker));
+        drop(wakers);
+        Faulty { trigger: self.clone(), waker, inner: Some(Box::pin(future)) }
+    }
+
+    /// Makes the wrapped futures suffer the injection.
+    pub fn fire(&self, injection: Injection) {
+        self.inner.injection.store(injection as usize, Ordering::Release);
+        let wakers = self.inner.wakers.lock().unwrap();
+        for waker in wakers.iter().filter_map(Weak::upgrade) {
+            waker.wake();
+        }
+    }
+
+    fn fired(&self) -> Option<Injection> {
+        match self.inner.injection.load(Ordering::Acquire) {
+            1 => Some(Injection::Panic),
+            2 => Some(Injection::Drop),
+            _ => None,
+        }
+    }
+}
+
+/// A future wrapped by a `Trigger`. Completes with `None` if it was
+/// dropped.
+#[derive(Debug)]
+pub struct Faulty<F> {
+    trigger: Trigger,
+    waker: Arc<AtomicWaker>,
+    inner: Option<Pin<Box<F>>>,
+}
+
+impl<F: Future> Future for Faulty<F> {
+    type Output = Option<F::Output>;
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.waker.register(ctx.waker());
+        match this.trigger.fired() {
+            Some(Injection::Panic) => panic!("Injected panic"),
+            Some(Injection::Drop) => {
+                this.inner = None;
+                Poll::Ready(None)
+            }
+            None => match this.inner.as_mut() {
+                Some(inner) => inner.as_mut().poll(ctx).map(Some),
+                None => Poll::Ready(None),
+            },
+        }
+    }
+}
+
+/// Randomly faults or shuts down the Devices it has been given, in
+/// an order determined by the seed.
+#[derive(Debug)]
+pub struct Chaos {
+    device: Device,
+    rng: Rng,
+    lines: Vec<Line>,
+}
+
+impl Chaos {
+    /// Creates a new Chaos. The same seed with the same Devices added
+    /// in the same order makes the same choices.
+    pub fn new(seed: u64) -> Chaos {
+        Chaos { device: Device::new(), rng: Rng::new(seed), lines: Vec::new() }
+    }
+
+    /// The ID injected messages come from.
+    pub fn device_id(&self) -> DeviceID {
+        self.device.device_id()
+    }
+
+    /// Makes the Device a candidate for sabotage.
+    pub fn add(&mut self, line: Line) {
+        self.lines.push(line);
+    }
+
+    /// Sends a random one of the Devices still alive a fault or a
+    /// shutdown request, returning what was sent to whom.
+    pub fn step(&mut self) -> Option<(DeviceID, Message)> {
+        self.lines.retain(|l| l.is_alive());
+        if self.lines.is_empty() {
+            return None;
+        }
+        let idx = self.rng.below(self.lines.len());
+        let me = self.device.device_id();
+        let message = match self.rng.below(4) {
+            0 => Disconnected(me, Some(Fault::Error)),
+            1 => Disconnected(me, Some(Fault::Timeout)),
+            2 => Disconnected(me, Some(Fault::Drop)),
+            _ => Shutdown(me),
+        };
+        let line = &self.lines[idx];
+        let did = line.device_id();
+        line.plugboard.send(message).ok()?;
+        Some((did, message))
+    }
+}
@@ -0,0 +1,131 @@
+//! Executor-neutral timing and timers which deliver to a Device.
+use crate::{Line, Message};
+use crate::Message::Tick;
+use atomic_waker::AtomicWaker;
+use core::future::Future;
+use futures_lite::FutureExt;
+use futures_micro::poll_fn;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+
+/// A source of delays. We don't have an opinion about which executor
+/// you use, so you plug in the timer that goes with it.
+///
+/// Implemented for closures, so e.g. with `async-io` you could pass
+/// `&|d| async move { async_io::Timer::after(d).await; }`.
+pub trait Timer {
+    /// The future returned by `delay()`.
+    type Delay: Future<Output = ()>;
+
+    /// Returns a future which completes after the provided duration.
+    fn delay(&self, duration: Duration) -> Self::Delay;
+}
+
+impl<F, D> Timer for F
+where
+    F: Fn(Duration) -> D,
+    D: Future<Output = ()>,
+{
+    type Delay = D;
+
+    fn delay(&self, duration: Duration) -> D {
+        self(duration)
+    }
+}
+
+/// A handle which can cancel a running timer. Dropping it does *not*
+/// cancel the timer.
+#[derive(Clone, Debug)]
+pub struct TimerRef {
+    inner: Arc<Cancel>,
+}
+
+#[derive(Debug, Default)]
+struct Cancel {
+    cancelled: AtomicBool,
+    waker: AtomicWaker,
+}
+
+impl TimerRef {
+    fn new() -> TimerRef {
+        TimerRef { inner: Arc::new(Cancel::default()) }
+    }
+
+    /// Stops the timer. It will not send any more messages.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::Release);
+        self.inner.waker.wake();
+    }
+
+    /// true if `cancel()` has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    // Completes with false when we are cancelled.
+    async fn cancelled(&self) -> bool {
+        poll_fn(|ctx| {
+            if self.is_cancelled() {
+                return Poll::Ready(false);
+            }
+            self.inner.waker.register(ctx.waker());
+            if self.is_cancelled() { Poll::Ready(false) } else { Poll::Pending }
+        })
+        .await
+    }
+
+    // Completes with true if the delay expired before we were cancelled.
+    async fn expired<D: Future<Output = ()>>(&self, delay: D) -> bool {
+        self.cancelled()
+            .or(async {
+                delay.await;
+                true
+            })
+            .await
+    }
+}
+
+/// Sends a message to a Device after a delay, unless cancelled.
+///
+/// Returns a handle to cancel it with and a future to spawn on your
+/// executor, which completes when the message has been sent or the
+/// timer has been cancelled. If the Device has disconnected, the
+/// message is silently discarded.
+pub fn send_after<T: Timer>(
+    timer: T,
+    line: Line,
+    message: Message,
+    after: Duration,
+) -> (TimerRef, impl Future<Output = ()>) {
+    let timer_ref = TimerRef::new();
+    let cancel = timer_ref.clone();
+    let fut = async move {
+        #[allow(unused_must_use)]
+        if cancel.expired(timer.delay(after)).await {
+            line.plugboard.send(message);
+        }
+    };
+    (timer_ref, fut)
+}
+
+/// Sends a Device `Tick`s, waiting `every` before each, until
+/// cancelled or the Device disconnects. `manage()` ignores ticks, so
+/// `watch()` for them.
+///
+/// Returns a handle to cancel it with and a future to spawn on your
+/// executor, which completes when the timer stops.
+pub fn interval<T: Timer>(timer: T, line: Line, every: Duration) -> (TimerRef, impl Future<Output = ()>) {
+    let timer_ref = TimerRef::new();
+    let cancel = timer_ref.clone();
+    let fut = async move {
+        let message = Tick(line.device_id());
+        while cancel.expired(timer.delay(every)).await {
+            if line.plugboard.send(message).is_err() {
+                return; // They're gone, nobody to tick for.
+            }
+        }
+    };
+    (timer_ref, fut)
+}
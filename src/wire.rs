@@ -0,0 +1,225 @@
+// The node protocol. Every frame is a big-endian u32 length followed
+// by a tag byte and the fields. DeviceIDs are written with the node
+// they live on filled in, so `here` is our node's ID.
+use crate::*;
+use std::io::{self, Read, Write};
+
+// Nobody has a good reason to send more than this.
+const MAX_FRAME: usize = 1 << 20;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Frame {
+    // The first frame either side sends.
+    Hello { node: u32 },
+    // Asks for the ID of the Device exported under a name.
+    Lookup { id: u64, name: String },
+    Found { id: u64, device: Option<DeviceID> },
+    // Let my Device know when yours disconnects.
+    Watch { watcher: DeviceID, target: DeviceID },
+    Unwatch { watcher: DeviceID, target: DeviceID },
+    // I will let your Device know when mine disconnects.
+    Watching { watcher: DeviceID, target: DeviceID },
+    Unwatching { watcher: DeviceID, target: DeviceID },
+    // My Device disconnected.
+    Disconnected { device: DeviceID, fault: Option<Fault> },
+    // Deliver to your Device.
+    Message { to: DeviceID, message: Message },
+    // I'm still here.
+    Tick,
+}
+
+pub(crate) fn write_frame<W: Write>(w: &mut W, frame: &Frame, here: u32) -> io::Result<()> {
+    let mut buf = vec![0, 0, 0, 0];
+    let mut e = Encoder { buf: &mut buf, here };
+    match frame {
+        Frame::Hello { node } => { e.u8(0); e.u32(*node); }
+        Frame::Lookup { id, name } => { e.u8(1); e.u64(*id); e.str(name); }
+        Frame::Found { id, device } => {
+            e.u8(2);
+            e.u64(*id);
+            match device {
+                Some(did) => { e.u8(1); e.did(*did); }
+                None => e.u8(0),
+            }
+        }
+        Frame::Watch { watcher, target } => { e.u8(3); e.did(*watcher); e.did(*target); }
+        Frame::Unwatch { watcher, target } => { e.u8(4); e.did(*watcher); e.did(*target); }
+        Frame::Watching { watcher, target } => { e.u8(5); e.did(*watcher); e.did(*target); }
+        Frame::Unwatching { watcher, target } => { e.u8(6); e.did(*watcher); e.did(*target); }
+        Frame::Disconnected { device, fault } => { e.u8(7); e.did(*device); e.fault(*fault); }
+        Frame::Message { to, message } => {
+            e.u8(8);
+            e.did(*to);
+            match message {
+                Disconnected(did, fault) => { e.u8(0); e.did(*did); e.fault(*fault); }
+                Shutdown(did) => { e.u8(1); e.did(*did); }
+                Tick(did) => { e.u8(2); e.did(*did); }
+            }
+        }
+        Frame::Tick => e.u8(9),
+    }
+    let len = (buf.len() - 4) as u32;
+    buf[..4].copy_from_slice(&len.to_be_bytes());
+    w.write_all(&buf)?;
+    w.flush()
+}
+
+pub(crate) fn read_frame<R: Read>(r: &mut R, here: u32) -> io::Result<Frame> {
+    let mut len = [0; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 || len > MAX_FRAME {
+        return Err(invalid());
+    }
+    let mut buf = vec![0; len];
+    r.read_exact(&mut buf)?;
+    let mut d = Decoder { buf: &buf, here };
+    let frame = match d.u8()? {
+        0 => Frame::Hello { node: d.u32()? },
+        1 => Frame::Lookup { id: d.u64()?, name: d.str()? },
+        2 => {
+            let id = d.u64()?;
+            let device = if d.u8()? == 1 { Some(d.did()?) } else { None };
+            Frame::Found { id, device }
+        }
+        3 => Frame::Watch { watcher: d.did()?, target: d.did()? },
+        4 => Frame::Unwatch { watcher: d.did()?, target: d.did()? },
+        5 => Frame::Watching { watcher: d.did()?, target: d.did()? },
+        6 => Frame::Unwatching { watcher: d.did()?, target: d.did()? },
+        7 => Frame::Disconnected { device: d.did()?, fault: d.fault()? },
+        8 => {
+            let to = d.did()?;
+            let message = match d.u8()? {
+                0 => Disconnected(d.did()?, d.fault()?),
+                1 => Shutdown(d.did()?),
+                2 => Tick(d.did()?),
+                _ => return Err(invalid()),
+            };
+            Frame::Message { to, message }
+        }
+        9 => Frame::Tick,
+        _ => return Err(invalid()),
+    };
+    if d.buf.is_empty() { Ok(frame) } else { Err(invalid()) }
+}
+
+fn invalid() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Malformed frame")
+}
+
+struct Encoder<'a> {
+    buf: &'a mut Vec<u8>,
+    here: u32,
+}
+
+impl Encoder<'_> {
+    fn u8(&mut self, v: u8) { self.buf.push(v); }
+    fn u32(&mut self, v: u32) { self.buf.extend_from_slice(&v.to_be_bytes()); }
+    fn u64(&mut self, v: u64) { self.buf.extend_from_slice(&v.to_be_bytes()); }
+
+    fn str(&mut self, v: &str) {
+        self.u32(v.len() as u32);
+        self.buf.extend_from_slice(v.as_bytes());
+    }
+
+    fn did(&mut self, did: DeviceID) {
+        self.u32(if did.is_local() { self.here } else { did.node });
+        self.u64(did.inner as u64);
+    }
+
+    fn fault(&mut self, fault: Option<Fault>) {
+        match fault {
+            None => self.u8(0),
+            Some(Fault::Drop) => self.u8(1),
+            Some(Fault::Error) => self.u8(2),
+            Some(Fault::Timeout) => self.u8(3),
+            Some(Fault::ConnectionLost) => self.u8(4),
+            Some(Fault::NodeDown) => self.u8(6),
+            Some(Fault::Cascade(did, origin)) => {
+                self.u8(5);
+                self.did(did);
+                self.did(origin.device);
+                self.u8(match origin.cause {
+                    Cause::Drop => 1,
+                    Cause::Error => 2,
+                    Cause::Timeout => 3,
+                    Cause::ConnectionLost => 4,
+                    Cause::NodeDown => 6,
+                });
+                self.u64(origin.hops as u64);
+            }
+        }
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+    here: u32,
+}
+
+impl Decoder<'_> {
+    fn take(&mut self, n: usize) -> io::Result<&[u8]> {
+        if self.buf.len() < n {
+            return Err(invalid());
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> { Ok(self.take(1)?[0]) }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut b = [0; 4];
+        b.copy_from_slice(self.take(4)?);
+        Ok(u32::from_be_bytes(b))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_be_bytes(b))
+    }
+
+    fn str(&mut self) -> io::Result<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid())
+    }
+
+    fn did(&mut self) -> io::Result<DeviceID> {
+        let node = self.u32()?;
+        let inner = self.u64()? as usize;
+        let node = if node == self.here { 0 } else { node };
+        Ok(DeviceID { node, inner })
+    }
+
+    fn cause(&mut self) -> io::Result<Cause> {
+        match self.u8()? {
+            1 => Ok(Cause::Drop),
+            2 => Ok(Cause::Error),
+            3 => Ok(Cause::Timeout),
+            4 => Ok(Cause::ConnectionLost),
+            6 => Ok(Cause::NodeDown),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn fault(&mut self) -> io::Result<Option<Fault>> {
+        Ok(match self.u8()? {
+            0 => None,
+            1 => Some(Fault::Drop),
+            2 => Some(Fault::Error),
+            3 => Some(Fault::Timeout),
+            4 => Some(Fault::ConnectionLost),
+            6 => Some(Fault::NodeDown),
+            5 => {
+                let did = self.did()?;
+                let device = self.did()?;
+                let cause = self.cause()?;
+                let hops = self.u64()? as usize;
+                Some(Fault::Cascade(did, Origin { device, cause, hops }))
+            }
+            _ => return Err(invalid()),
+        })
+    }
+}
@@ -0,0 +1,218 @@
+#![cfg(feature = "std")]
+use async_backplane::prelude::*;
+use futures_lite::future::{block_on, pending, ready};
+use std::sync::{Arc, Mutex};
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+// A child which logs its start and stop and otherwise runs forever.
+fn forever(
+    log: &Log,
+    name: &'static str,
+) -> impl FnOnce(Device) -> futures_lite::future::Boxed<()> {
+    let log = log.clone();
+    move |device| {
+        Box::pin(async move {
+            log.lock().unwrap().push(format!("start {}", name));
+            device.ready();
+            let r: Result<(), Crash<()>> = device.manage(pending()).await;
+            assert!(matches!(r, Err(Crash::PowerOff(_))));
+            log.lock().unwrap().push(format!("stop {}", name));
+        })
+    }
+}
+
+#[test]
+fn starts_in_order_stops_in_reverse() {
+    let log = Log::default();
+    let app = Device::new();
+    let line = app.line();
+    let shutdown = app.line();
+    let r = block_on(
+        Application::new()
+            .child("c", &["b"], move |device| async move {
+                device.ready();
+                shutdown.send(Shutdown(device.device_id())).unwrap();
+                let _: Result<(), Crash<()>> = device.manage(pending()).await;
+            })
+            .child("b", &["a"], forever(&log, "b"))
+            .child("a", &[], forever(&log, "a"))
+            .run(app),
+    );
+    assert_eq!(Ok(()), r);
+    assert_eq!(
+        vec!["start a", "start b", "stop b", "stop a"],
+        *log.lock().unwrap()
+    );
+    assert_eq!(None, block_on(line.disconnected()));
+}
+
+#[test]
+fn all_children_complete() {
+    let app = Device::new();
+    let line = app.line();
+    let r = block_on(
+        Application::new()
+            .child("a", &[], |device| async move {
+                device.ready();
+                let _: Result<(), Crash<()>> = device.manage(ready(Ok(()))).await;
+            })
+            .run(app),
+    );
+    assert_eq!(Ok(()), r);
+    assert_eq!(None, block_on(line.disconnected()));
+}
+
+#[test]
+fn child_not_ready() {
+    let log = Log::default();
+    let app = Device::new();
+    let line = app.line();
+    let r = block_on(
+        Application::new()
+            .child("a", &[], forever(&log, "a"))
+            .child("b", &["a"], |device| async move { drop(device) })
+            .child("c", &["b"], forever(&log, "c"))
+            .run(app),
+    );
+    assert_eq!(Err(AppError::NotReady("b".to_string(), Some(Fault::Drop))), r);
+    assert_eq!(vec!["start a", "stop a"], *log.lock().unwrap());
+    assert!(block_on(line.disconnected()).unwrap().is_cascade());
+}
+
+#[test]
+fn child_faults() {
+    let log = Log::default();
+    let app = Device::new();
+    let line = app.line();
+    let r = block_on(
+        Application::new()
+            .child("a", &[], forever(&log, "a"))
+            .child("b", &["a"], |device| async move {
+                device.ready();
+                let _: Result<(), Crash<()>> = device.manage(ready(Err(()))).await;
+            })
+            .run(app),
+    );
+    assert_eq!(Err(AppError::Faulted("b".to_string(), Fault::Error)), r);
+    assert_eq!(vec!["start a", "stop a"], *log.lock().unwrap());
+    assert!(block_on(line.disconnected()).unwrap().is_cascade());
+}
+
+#[test]
+fn dependency_faults() {
+    let log = Log::default();
+    let app = Device::new();
+    let r = block_on(
+        Application::new()
+            .child("a", &[], |device| async move {
+                device.ready();
+                let _: Result<(), Crash<()>> = device.manage(ready(Err(()))).await;
+            })
+            .child("b", &["a"], forever(&log, "b"))
+            .run(app),
+    );
+    assert_eq!(Err(AppError::Faulted("a".to_string(), Fault::Error)), r);
+    assert!(log.lock().unwrap().is_empty());
+}
+
+#[test]
+fn unknown_dependency() {
+    let log = Log::default();
+    let app = Device::new();
+    let line = app.line();
+    let r = block_on(
+        Application::new()
+            .child("a", &["z"], forever(&log, "a"))
+            .run(app),
+    );
+    let err = AppError::UnknownDependency("a".to_string(), "z".to_string());
+    assert_eq!(Err(err), r);
+    assert!(log.lock().unwrap().is_empty());
+    assert_eq!(Some(Fault::Error), block_on(line.disconnected()));
+}
+
+#[test]
+fn cycle() {
+    let log = Log::default();
+    let app = Device::new();
+    let r = block_on(
+        Application::new()
+            .child("a", &["b"], forever(&log, "a"))
+            .child("b", &["a"], forever(&log, "b"))
+            .run(app),
+    );
+    assert_eq!(Err(AppError::Cycle("a".to_string())), r);
+    assert!(log.lock().unwrap().is_empty());
+}
+
+#[test]
+fn depends_on_cycle() {
+    let log = Log::default();
+    let app = Device::new();
+    let r = block_on(
+        Application::new()
+            .child("a", &["b"], forever(&log, "a"))
+            .child("b", &["c"], forever(&log, "b"))
+            .child("c", &["b"], forever(&log, "c"))
+            .run(app),
+    );
+    assert_eq!(Err(AppError::Cycle("b".to_string())), r);
+    assert!(log.lock().unwrap().is_empty());
+}
+
+#[test]
+fn shutdown_while_starting() {
+    let log = Log::default();
+    let app = Device::new();
+    let shutdown = app.line();
+    let r = block_on(
+        Application::new()
+            .child("a", &[], forever(&log, "a"))
+            .child("b", &["a"], move |device| async move {
+                shutdown.send(Shutdown(device.device_id())).unwrap();
+                let _: Result<(), Crash<()>> = device.manage(pending()).await;
+            })
+            .child("c", &["b"], forever(&log, "c"))
+            .run(app),
+    );
+    assert_eq!(Ok(()), r);
+    assert_eq!(vec!["start a", "stop a"], *log.lock().unwrap());
+}
+
+#[test]
+fn fault_while_starting() {
+    let app = Device::new();
+    // Dropped once "b" has started.
+    let started = Device::new();
+    let line = started.line();
+    let r = block_on(
+        Application::new()
+            .child("a", &[], move |device| async move {
+                device.ready();
+                line.disconnected().await;
+                let _: Result<(), Crash<()>> = device.manage(ready(Err(()))).await;
+            })
+            .child("b", &[], move |device| async move {
+                drop(started);
+                let _: Result<(), Crash<()>> = device.manage(pending()).await;
+            })
+            .run(app),
+    );
+    assert_eq!(Err(AppError::Faulted("a".to_string(), Fault::Error)), r);
+}
+
+#[test]
+fn duplicate_name() {
+    let log = Log::default();
+    let app = Device::new();
+    let r = block_on(
+        Application::new()
+            .child("a", &[], forever(&log, "a"))
+            .child("b", &["a"], forever(&log, "b"))
+            .child("a", &[], forever(&log, "a"))
+            .run(app),
+    );
+    assert_eq!(Err(AppError::DuplicateName("a".to_string())), r);
+    assert!(log.lock().unwrap().is_empty());
+}
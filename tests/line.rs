@@ -0,0 +1,175 @@
+use async_backplane::prelude::*;
+use futures_lite::future::{block_on, poll_once};
+use std::thread::spawn;
+
+#[test]
+fn alive_until_disconnect() {
+    let d = Device::new();
+    let line = d.line();
+    assert!(line.is_alive());
+    d.disconnect(None);
+    assert!(!line.is_alive());
+}
+
+#[test]
+fn alive_until_drop() {
+    let d = Device::new();
+    let line = d.line();
+    assert!(line.is_alive());
+    drop(d);
+    assert!(!line.is_alive());
+}
+
+#[test]
+fn disconnected_after_succeeds() {
+    let d = Device::new();
+    let line = d.line();
+    d.disconnect(None);
+    assert_eq!(None, block_on(line.disconnected()));
+}
+
+#[test]
+fn disconnected_after_errors() {
+    let d = Device::new();
+    let line = d.line();
+    d.disconnect(Some(Fault::Error));
+    assert_eq!(Some(Fault::Error), block_on(line.disconnected()));
+}
+
+#[test]
+fn disconnected_after_drops() {
+    let d = Device::new();
+    let line = d.line();
+    drop(d);
+    assert_eq!(Some(Fault::Drop), block_on(line.disconnected()));
+}
+
+#[test]
+fn disconnected_waits() {
+    let d = Device::new();
+    let l1 = d.line();
+    let l2 = d.line();
+    let w1 = spawn(move || block_on(l1.disconnected()));
+    let w2 = spawn(move || block_on(l2.disconnected()));
+    spawn(move || d.disconnect(Some(Fault::Error))).join().unwrap();
+    assert_eq!(Some(Fault::Error), w1.join().unwrap());
+    assert_eq!(Some(Fault::Error), w2.join().unwrap());
+}
+
+#[test]
+fn disconnected_polled_often() {
+    let d = Device::new();
+    let line = d.line();
+    let mut fut = Box::pin(line.disconnected());
+    block_on(async {
+        for _ in 0..100 {
+            assert_eq!(None, poll_once(&mut fut).await);
+        }
+    });
+    d.disconnect(Some(Fault::Error));
+    assert_eq!(Some(Fault::Error), block_on(fut));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn disconnected_after_managed_cascade() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let i1 = d1.device_id();
+    let line = d2.line();
+    d2.link(&d1, LinkMode::Monitor);
+    d1.disconnect(Some(Fault::Error));
+    let result: Result<(), Crash<()>> = block_on(d2.manage(futures_lite::future::pending()));
+    assert!(result.unwrap_err().is_cascade());
+    assert_eq!(Some(Fault::cascade(i1, Fault::Error)), block_on(line.disconnected()));
+}
+
+#[test]
+fn ready_before_waiting() {
+    let d = Device::new();
+    let line = d.line();
+    assert!(!line.is_ready());
+    d.ready();
+    assert!(line.is_ready());
+    assert_eq!(Ok(()), block_on(line.ready()));
+}
+
+#[test]
+fn ready_waits() {
+    let d = Device::new();
+    let line = d.line();
+    let w = spawn(move || block_on(line.ready()));
+    spawn(move || {
+        d.ready();
+        d
+    })
+    .join()
+    .unwrap();
+    assert_eq!(Ok(()), w.join().unwrap());
+}
+
+#[test]
+fn ready_then_disconnected() {
+    let d = Device::new();
+    let line = d.line();
+    d.ready();
+    d.disconnect(Some(Fault::Error));
+    assert_eq!(Ok(()), block_on(line.ready()));
+}
+
+#[test]
+fn never_ready() {
+    let d = Device::new();
+    let line = d.line();
+    let w = spawn(move || block_on(line.ready()));
+    spawn(move || drop(d)).join().unwrap();
+    assert_eq!(Err(ReadyError::Disconnected(Some(Fault::Drop))), w.join().unwrap());
+}
+
+#[test]
+fn never_ready_succeeds() {
+    let d = Device::new();
+    let line = d.line();
+    d.disconnect(None);
+    assert_eq!(Err(ReadyError::Disconnected(None)), block_on(line.ready()));
+}
+
+#[test]
+fn send_by_reference() {
+    let d = Device::new();
+    let line = d.line();
+    let me = d.device_id();
+    line.send(Shutdown(me)).unwrap();
+    line.send(Shutdown(me)).unwrap();
+    assert_eq!(Some(Shutdown(me)), d.receive());
+    assert_eq!(Some(Shutdown(me)), d.receive());
+    assert_eq!(None, d.receive());
+}
+
+#[test]
+fn try_send_closed() {
+    let d = Device::new();
+    let line = d.line();
+    let me = d.device_id();
+    assert_eq!(Ok(()), line.try_send(Shutdown(me)));
+    d.disconnect(None);
+    let err = line.try_send(Shutdown(me)).unwrap_err();
+    assert!(err.is_closed());
+    assert!(!err.is_full());
+    assert_eq!(Shutdown(me), err.into_message());
+    assert_eq!(Err(Shutdown(me)), line.send(Shutdown(me)));
+}
+
+#[test]
+fn broadcast_skips_disconnected() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    let me = d1.device_id();
+    let lines = vec![d1.line(), d2.line(), d3.line()];
+    d2.disconnect(None);
+    assert_eq!(2, Line::broadcast(&lines, Shutdown(me)));
+    assert_eq!(Some(Shutdown(me)), d1.receive());
+    assert_eq!(Some(Shutdown(me)), d3.receive());
+    assert_eq!(0, Line::broadcast(&[], Shutdown(me)));
+}
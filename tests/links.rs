@@ -0,0 +1,274 @@
+use async_backplane::prelude::*;
+
+#[test]
+fn no_links() {
+    let d = Device::new();
+    assert!(d.links().is_empty());
+}
+
+#[test]
+fn device_links() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    d1.link(&d2, LinkMode::Monitor);
+    d1.link(&d3, LinkMode::Peer);
+    let mut links = d1.links();
+    links.sort_by_key(|(did, _)| *did);
+    let mut expected = vec![
+        (d2.device_id(), LinkMode::Monitor),
+        (d3.device_id(), LinkMode::Peer),
+    ];
+    expected.sort_by_key(|(did, _)| *did);
+    assert_eq!(expected, links);
+    assert_eq!(vec![(d1.device_id(), LinkMode::Notify)], d2.links());
+    assert_eq!(vec![(d1.device_id(), LinkMode::Peer)], d3.links());
+}
+
+#[test]
+fn device_unlinks() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    d1.link(&d2, LinkMode::Peer);
+    d1.unlink(&d2, LinkMode::Notify);
+    assert_eq!(vec![(d2.device_id(), LinkMode::Monitor)], d1.links());
+    assert_eq!(vec![(d1.device_id(), LinkMode::Notify)], d2.links());
+    d1.unlink(&d2, LinkMode::Monitor);
+    assert!(d1.links().is_empty());
+    assert!(d2.links().is_empty());
+}
+
+#[test]
+fn line_links() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    d1.line().link_line(d2.line(), LinkMode::Monitor).unwrap();
+    assert_eq!(Some(LinkMode::Monitor), d1.link_mode(&d2.line()));
+    assert_eq!(Some(LinkMode::Notify), d2.link_mode(&d1.line()));
+    d2.link_line(d1.line(), LinkMode::Monitor).unwrap();
+    assert_eq!(Some(LinkMode::Peer), d1.link_mode(&d2.line()));
+    assert_eq!(Some(LinkMode::Peer), d2.link_mode(&d1.line()));
+    assert_eq!(Ok(()), d2.unlink_line(&d1.line(), LinkMode::Peer));
+    assert_eq!(None, d1.link_mode(&d2.line()));
+    assert_eq!(None, d2.link_mode(&d1.line()));
+}
+
+#[test]
+fn disconnected_are_omitted() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    d1.link(&d2, LinkMode::Peer);
+    d2.disconnect(None);
+    assert!(d1.links().is_empty());
+}
+
+#[test]
+fn device_links_many() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    d1.link(&d2, LinkMode::Monitor);
+    d1.link_many(&[&d2, &d3, &d3], LinkMode::Peer);
+    let mut links = d1.links();
+    links.sort_by_key(|(did, _)| *did);
+    let mut expected = vec![
+        (d2.device_id(), LinkMode::Peer),
+        (d3.device_id(), LinkMode::Peer),
+    ];
+    expected.sort_by_key(|(did, _)| *did);
+    assert_eq!(expected, links);
+    assert_eq!(vec![(d1.device_id(), LinkMode::Peer)], d2.links());
+    assert_eq!(vec![(d1.device_id(), LinkMode::Peer)], d3.links());
+}
+
+#[test]
+fn line_links_many() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    let d4 = Device::new();
+    let l4 = d4.line();
+    d4.disconnect(None);
+    let down = d1
+        .link_lines(vec![d2.line(), l4.clone(), d3.line()], LinkMode::Monitor)
+        .unwrap_err();
+    assert_eq!(vec![l4], down);
+    assert_eq!(Some(LinkMode::Monitor), d1.link_mode(&d2.line()));
+    assert_eq!(Some(LinkMode::Monitor), d1.link_mode(&d3.line()));
+    assert_eq!(Some(LinkMode::Notify), d3.link_mode(&d1.line()));
+    assert_eq!(Ok(()), d1.link_lines(vec![d2.line()], LinkMode::Notify));
+    assert_eq!(Some(LinkMode::Peer), d1.link_mode(&d2.line()));
+}
+
+#[test]
+fn line_notifies_many() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    let l3 = d3.line();
+    d3.disconnect(None);
+    let down = d1.link_lines(vec![l3.clone(), d2.line()], LinkMode::Notify).unwrap_err();
+    assert_eq!(vec![l3.clone()], down);
+    assert_eq!(None, d1.link_mode(&l3));
+    assert_eq!(Some(LinkMode::Notify), d1.link_mode(&d2.line()));
+    assert_eq!(Some(LinkMode::Monitor), d2.link_mode(&d1.line()));
+}
+
+#[test]
+fn line_notifies_dead() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let l2 = d2.line();
+    d2.disconnect(None);
+    assert_eq!(Err(LinkError::LinkDown), d1.link_line(l2.clone(), LinkMode::Notify));
+    assert_eq!(Err(vec![l2.clone()]), d1.link_lines(vec![l2.clone()], LinkMode::Notify));
+    assert_eq!(Err(LinkError::LinkDown), d1.line().link_line(l2.clone(), LinkMode::Notify));
+    assert_eq!(None, d1.link_mode(&l2));
+    assert!(d1.links().is_empty());
+}
+
+#[test]
+fn line_links_many_to_self() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let lines = vec![d2.line(), d1.line()];
+    assert_eq!(Err(LinkError::SelfLink), d1.try_link_lines(lines, LinkMode::Peer));
+    assert_eq!(Err(LinkError::SelfLink), d1.try_link_many(&[&d2, &d1], LinkMode::Peer));
+    assert!(d1.links().is_empty());
+    assert!(d2.links().is_empty());
+}
+
+#[test]
+fn self_links_fail() {
+    let d = Device::new();
+    assert_eq!(Err(LinkError::SelfLink), d.try_link(&d, LinkMode::Peer));
+    assert_eq!(Err(LinkError::SelfLink), d.try_unlink(&d, LinkMode::Peer));
+    assert_eq!(Err(LinkError::SelfLink), d.try_link_line(d.line(), LinkMode::Peer));
+    assert_eq!(Err(LinkError::SelfLink), d.try_unlink_line(&d.line(), LinkMode::Peer));
+    assert_eq!(Err(LinkError::SelfLink), d.line().try_link_line(d.line(), LinkMode::Peer));
+    assert_eq!(Err(LinkError::SelfLink), d.try_link_many(&[&d], LinkMode::Peer));
+    assert_eq!(Err(LinkError::SelfLink), d.try_link_lines(vec![d.line()], LinkMode::Peer));
+    assert!(d.links().is_empty());
+}
+
+#[test]
+#[should_panic]
+fn self_link_panics() {
+    let d = Device::new();
+    d.link(&d, LinkMode::Monitor);
+}
+
+// Linking twice is the same as linking once, whichever way we link.
+fn assert_linked_once(d1: Device, d2: Device, mode: LinkMode) {
+    let theirs = match mode {
+        LinkMode::Monitor => LinkMode::Notify,
+        LinkMode::Notify => LinkMode::Monitor,
+        LinkMode::Peer => LinkMode::Peer,
+    };
+    assert_eq!(vec![(d2.device_id(), mode)], d1.links());
+    assert_eq!(vec![(d1.device_id(), theirs)], d2.links());
+    let (watcher, watched) = if mode.monitor() { (d1, d2) } else { (d2, d1) };
+    let id = watched.device_id();
+    watched.disconnect(None);
+    assert_eq!(Some(Disconnected(id, None)), watcher.receive());
+    assert_eq!(None, watcher.receive());
+}
+
+// Links twice in each mode, then upgrades Monitor to Peer.
+fn check_duplicates<F: Fn(&Device, &Device, LinkMode)>(link: F) {
+    for mode in [LinkMode::Monitor, LinkMode::Notify, LinkMode::Peer].iter().copied() {
+        let d1 = Device::new();
+        let d2 = Device::new();
+        link(&d1, &d2, mode);
+        link(&d1, &d2, mode);
+        assert_linked_once(d1, d2, mode);
+    }
+    let d1 = Device::new();
+    let d2 = Device::new();
+    link(&d1, &d2, LinkMode::Monitor);
+    link(&d1, &d2, LinkMode::Peer);
+    assert_linked_once(d1, d2, LinkMode::Peer);
+}
+
+#[test]
+fn duplicate_device_links() {
+    check_duplicates(|d1, d2, mode| d1.link(d2, mode));
+    check_duplicates(|d1, d2, mode| d1.try_link(d2, mode).unwrap());
+}
+
+#[test]
+fn duplicate_line_links() {
+    check_duplicates(|d1, d2, mode| d1.link_line(d2.line(), mode).unwrap());
+    check_duplicates(|d1, d2, mode| d1.try_link_line(d2.line(), mode).unwrap());
+}
+
+#[test]
+fn duplicate_line_line_links() {
+    check_duplicates(|d1, d2, mode| d1.line().link_line(d2.line(), mode).unwrap());
+    check_duplicates(|d1, d2, mode| d1.line().try_link_line(d2.line(), mode).unwrap());
+}
+
+#[test]
+fn duplicate_many_links() {
+    check_duplicates(|d1, d2, mode| d1.link_many(&[d2], mode));
+    check_duplicates(|d1, d2, mode| d1.try_link_many(&[d2], mode).unwrap());
+    check_duplicates(|d1, d2, mode| d1.link_lines(vec![d2.line()], mode).unwrap());
+    check_duplicates(|d1, d2, mode| {
+        assert!(d1.try_link_lines(vec![d2.line()], mode).unwrap().is_empty());
+    });
+}
+
+#[test]
+fn duplicate_mixed_links() {
+    check_duplicates(|d1, d2, mode| {
+        d1.link(d2, mode);
+        d1.link_line(d2.line(), mode).unwrap();
+        d1.line().link_line(d2.line(), mode).unwrap();
+        d1.link_many(&[d2], mode);
+        d1.link_lines(vec![d2.line()], mode).unwrap();
+    });
+}
+
+#[test]
+fn duplicate_unlinks() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    d1.link(&d2, LinkMode::Peer);
+    d1.unlink(&d2, LinkMode::Peer);
+    d1.try_unlink(&d2, LinkMode::Peer).unwrap();
+    assert_eq!(Err(LinkError::NotLinked), d1.unlink_line(&d2.line(), LinkMode::Peer));
+    assert!(d1.links().is_empty());
+    assert!(d2.links().is_empty());
+}
+
+#[test]
+fn line_unlinks_report() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    assert_eq!(Err(LinkError::NotLinked), d1.unlink_line(&d2.line(), LinkMode::Monitor));
+    d2.line().link_line(d1.line(), LinkMode::Notify).unwrap();
+    assert_eq!(Ok(()), d1.unlink_line(&d2.line(), LinkMode::Monitor));
+    assert_eq!(Err(LinkError::SelfLink), d1.line().unlink_line(&d1.line(), LinkMode::Peer));
+    d1.link_line(d3.line(), LinkMode::Monitor).unwrap();
+    let l3 = d3.line();
+    d3.disconnect(None);
+    assert_eq!(Err(LinkError::LinkDown), d1.unlink_line(&l3, LinkMode::Monitor));
+    assert_eq!(Err(LinkError::LinkDown), d1.line().unlink_line(&l3, LinkMode::Monitor));
+    let l1 = d1.line();
+    d1.disconnect(None);
+    assert_eq!(Err(LinkError::DeviceDown), l1.unlink_line(&d2.line(), LinkMode::Monitor));
+}
+
+#[test]
+fn receiving_disconnect_unlinks() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let i2 = d2.device_id();
+    let l2 = d2.line();
+    d1.link_line(l2.clone(), LinkMode::Peer).unwrap();
+    d2.disconnect(None);
+    assert_eq!(Some(LinkMode::Peer), d1.link_mode(&l2));
+    assert_eq!(Some(Disconnected(i2, None)), d1.receive());
+    assert_eq!(None, d1.link_mode(&l2));
+}
@@ -0,0 +1,187 @@
+use async_backplane::prelude::*;
+use futures_lite::future::block_on;
+#[cfg(feature = "std")]
+use futures_lite::future::{pending, ready};
+use futures_lite::StreamExt;
+#[cfg(feature = "std")]
+use std::thread::spawn;
+
+#[cfg(feature = "std")]
+fn assert_cascade(ret: Result<(), Crash<()>>, did: DeviceID, fault: Fault) {
+    if let Crash::Cascade(d, f) = ret.unwrap_err() {
+        assert_eq!(did, d);
+        assert_eq!(fault, f);
+    } else {
+        unreachable!()
+    }
+}
+
+#[test]
+fn local_links() {
+    let d1 = LocalDevice::new();
+    let d2 = LocalDevice::new();
+    d1.link(&d2, LinkMode::Monitor);
+    assert_eq!(vec![(d2.device_id(), LinkMode::Monitor)], d1.links());
+    assert_eq!(vec![(d1.device_id(), LinkMode::Notify)], d2.links());
+    d2.link_line(d1.line(), LinkMode::Monitor).unwrap();
+    assert_eq!(Some(LinkMode::Peer), d1.link_mode(&d2.line()));
+    d1.unlink(&d2, LinkMode::Peer);
+    assert!(d1.links().is_empty());
+    assert!(d2.links().is_empty());
+    assert_eq!(Err(LinkError::NotLinked), d1.unlink_line(&d2.line(), LinkMode::Peer));
+    let line = d2.line();
+    d2.disconnect(None);
+    assert_eq!(Err(LinkError::LinkDown), d1.unlink_line(&line, LinkMode::Peer));
+}
+
+#[test]
+fn local_disconnect_notifies() {
+    let mut d1 = LocalDevice::new();
+    let d2 = LocalDevice::new();
+    let i2 = d2.device_id();
+    d1.link(&d2, LinkMode::Monitor);
+    d2.disconnect(Some(Fault::Error));
+    assert_eq!(
+        Some(Disconnected(i2, Some(Fault::Error))),
+        block_on(d1.next())
+    );
+    assert!(d1.links().is_empty());
+}
+
+#[test]
+fn local_drop_notifies() {
+    let d1 = LocalDevice::new();
+    let d2 = LocalDevice::new();
+    let i2 = d2.device_id();
+    d1.link(&d2, LinkMode::Monitor);
+    let line = d2.line();
+    drop(d2);
+    assert!(!line.is_alive());
+    assert_eq!(Some(Fault::Drop), block_on(line.disconnected()));
+    assert_eq!(Some(Disconnected(i2, Some(Fault::Drop))), d1.receive());
+}
+
+#[test]
+fn local_self_link() {
+    let d = LocalDevice::new();
+    assert_eq!(Err(LinkError::SelfLink), d.line().try_link_line(d.line(), LinkMode::Peer));
+    assert_eq!(Err(LinkError::SelfLink), d.try_link(&d, LinkMode::Peer));
+    assert!(d.links().is_empty());
+}
+
+#[test]
+fn local_duplicate_links() {
+    // Each mode twice, then Monitor upgraded to Peer.
+    let cases = [
+        (LinkMode::Monitor, LinkMode::Monitor),
+        (LinkMode::Notify, LinkMode::Notify),
+        (LinkMode::Peer, LinkMode::Peer),
+        (LinkMode::Monitor, LinkMode::Peer),
+    ];
+    for (first, then) in cases.iter().copied() {
+        let d1 = LocalDevice::new();
+        let d2 = LocalDevice::new();
+        d1.link(&d2, first);
+        d1.try_link(&d2, then).unwrap();
+        d1.link_line(d2.line(), then).unwrap();
+        assert_eq!(vec![(d2.device_id(), then)], d1.links());
+    }
+}
+
+#[test]
+fn local_link_down() {
+    let d1 = LocalDevice::new();
+    let d2 = LocalDevice::new();
+    let line = d2.line();
+    d2.disconnect(None);
+    assert_eq!(Err(LinkError::LinkDown), d1.link_line(line, LinkMode::Monitor));
+}
+
+#[test]
+fn local_ready() {
+    let d = LocalDevice::new();
+    let line = d.line();
+    assert!(!line.is_ready());
+    d.ready();
+    assert_eq!(Ok(()), block_on(line.ready()));
+    let d = LocalDevice::new();
+    let line = d.line();
+    d.disconnect(None);
+    assert_eq!(Err(ReadyError::Disconnected(None)), block_on(line.ready()));
+}
+
+#[test]
+fn local_send() {
+    let d1 = LocalDevice::new();
+    let d2 = LocalDevice::new();
+    let i2 = d2.device_id();
+    d1.line().send(Shutdown(i2)).unwrap();
+    assert_eq!(Some(Shutdown(i2)), d1.receive());
+    assert_eq!(None, d1.receive());
+    let line = d1.line();
+    assert_eq!(1, LocalLine::broadcast(vec![&line], Shutdown(i2)));
+    assert_eq!(Some(Shutdown(i2)), d1.receive());
+    d1.disconnect(None);
+    assert_eq!(Err(Shutdown(i2)), line.send(Shutdown(i2)));
+    assert_eq!(Err(SendError::Closed(Shutdown(i2))), line.try_send(Shutdown(i2)));
+    assert_eq!(1, LocalLine::broadcast(&[line, d2.line()], Shutdown(i2)));
+    assert_eq!(Some(Shutdown(i2)), d2.receive());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn local_manage() {
+    let d1 = LocalDevice::new();
+    let d2 = LocalDevice::new();
+    let i2 = d2.device_id();
+    d1.link(&d2, LinkMode::Peer);
+    let line = d1.line();
+    block_on(d2.manage(ready(Ok::<(), ()>(())))).unwrap();
+    assert!(line.is_alive());
+    assert_eq!(Some(Disconnected(i2, None)), d1.receive());
+    let d2 = LocalDevice::new();
+    let i2 = d2.device_id();
+    d1.link(&d2, LinkMode::Peer);
+    let ret = block_on(d1.manage(Box::pin(async {
+        d2.disconnect(Some(Fault::Error));
+        pending().await
+    })));
+    assert_cascade(ret, i2, Fault::Error);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn shared_monitors_local() {
+    let d1 = Device::new();
+    let d2 = LocalDevice::new();
+    let i2 = d2.device_id();
+    d1.link_line(d2.shared_line(), LinkMode::Monitor).unwrap();
+    assert_eq!(vec![(d1.device_id(), LinkMode::Notify)], d2.links());
+    let t = spawn(move || block_on(d1.manage(pending())));
+    d2.disconnect(Some(Fault::Error));
+    assert_cascade(t.join().unwrap(), i2, Fault::Error);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn local_monitors_shared() {
+    let d1 = LocalDevice::new();
+    let d2 = Device::new();
+    let i2 = d2.device_id();
+    d1.link_shared(d2.line(), LinkMode::Monitor).unwrap();
+    assert_eq!(vec![(i2, LinkMode::Monitor)], d1.links());
+    spawn(move || d2.disconnect(Some(Fault::Error))).join().unwrap();
+    assert_cascade(block_on(d1.manage(pending())), i2, Fault::Error);
+}
+
+#[test]
+fn local_receiving_disconnect_unlinks() {
+    let d1 = LocalDevice::new();
+    let d2 = LocalDevice::new();
+    let l2 = d2.line();
+    d1.link(&d2, LinkMode::Peer);
+    d2.disconnect(None);
+    assert_eq!(Some(LinkMode::Peer), d1.link_mode(&l2));
+    assert!(d1.receive().is_some());
+    assert_eq!(None, d1.link_mode(&l2));
+}
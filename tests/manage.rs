@@ -1,3 +1,4 @@
+#![cfg(feature = "std")]
 use async_backplane::prelude::*;
 use futures_lite::future::{block_on, pending, ready};
 use std::thread::{spawn, JoinHandle};
@@ -73,7 +74,7 @@ fn monitored_device_errors() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Error)))), r3);
 }
 
 #[test]
@@ -93,7 +94,7 @@ fn monitored_line_errors() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Error)))), r3);
 }
 
 #[test]
@@ -114,7 +115,7 @@ fn monitored_device_drops() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Drop)))), r3);
 }
 
 #[test]
@@ -135,7 +136,7 @@ fn monitored_line_drops() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Drop)))), r3);
 }
 
 #[test]
@@ -187,7 +188,7 @@ fn peer_device_errors() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Error)))), r3);
 }
 
 #[test]
@@ -207,7 +208,7 @@ fn peer_line_errors() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Error)))), r3);
 }
 
 #[test]
@@ -228,7 +229,7 @@ fn peer_device_drops() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Drop)))), r3);
 }
 
 #[test]
@@ -249,5 +250,29 @@ fn peer_link_drops() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Drop)))), r3);
+}
+
+#[test]
+fn cascades_carry_root_cause() {
+    let devices: Vec<Device> = (0..5).map(|_| Device::new()).collect();
+    let ids: Vec<DeviceID> = devices.iter().map(|d| d.device_id()).collect();
+    for pair in devices.windows(2) {
+        pair[1].link(&pair[0], LinkMode::Monitor);
+    }
+    let mut devices = devices.into_iter();
+    devices.next().unwrap().disconnect(Some(Fault::Timeout));
+    let mut last = None;
+    for d in devices {
+        last = Some(fail(d).join().unwrap().unwrap_err());
+    }
+    let crash = last.unwrap();
+    let origin = Origin { device: ids[0], cause: Cause::Timeout, hops: 4 };
+    assert_eq!(Some(origin), crash.origin());
+    if let Crash::Cascade(did, fault) = crash {
+        assert_eq!(ids[3], did);
+        assert_eq!(Some(Origin { hops: 3, ..origin }), fault.origin());
+    } else {
+        panic!()
+    }
 }
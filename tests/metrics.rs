@@ -0,0 +1,54 @@
+#![cfg(feature = "metrics")]
+use async_backplane::metrics;
+use async_backplane::prelude::*;
+
+// Tests run in parallel, so we can only check the counters grew by
+// at least as much as we expected.
+
+#[test]
+fn counts_devices() {
+    let before = metrics::snapshot();
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    let d4 = Device::new();
+    let d5 = Device::new();
+    let i3 = d3.device_id();
+    d1.link(&d2, LinkMode::Monitor);
+    d1.disconnect(None);
+    drop(d2);
+    d3.disconnect(Some(Fault::Error));
+    d4.disconnect(Some(Fault::Timeout));
+    d5.disconnect(Some(Fault::cascade(i3, Fault::Error)));
+    let after = metrics::snapshot();
+    assert!(after.created >= before.created + 5);
+    assert!(after.disconnected_clean > before.disconnected_clean);
+    assert!(after.disconnected_drop > before.disconnected_drop);
+    assert!(after.disconnected_error > before.disconnected_error);
+    assert!(after.disconnected_timeout > before.disconnected_timeout);
+    assert!(after.cascades > before.cascades);
+}
+
+#[test]
+fn counts_messages() {
+    let before = metrics::snapshot();
+    let d1 = Device::new();
+    let d2 = Device::new();
+    d1.line().send(Shutdown(d2.device_id())).unwrap();
+    d1.line().send(Shutdown(d2.device_id())).unwrap();
+    assert!(metrics::snapshot().messages_sent >= before.messages_sent + 2);
+    assert_eq!(Some(Shutdown(d2.device_id())), d1.receive());
+    let line = d1.line();
+    d1.disconnect(None);
+    assert!(line.send(Shutdown(d2.device_id())).is_err());
+    assert!(metrics::snapshot().messages_failed > before.messages_failed);
+}
+
+#[test]
+fn prometheus() {
+    let text = metrics::snapshot().to_prometheus();
+    assert!(text.contains("# TYPE backplane_devices_created_total counter\n"));
+    assert!(text.contains("# TYPE backplane_devices_live gauge\n"));
+    assert!(text.contains("backplane_disconnects_total{fault=\"cascade\"} "));
+    assert!(text.contains("\nbackplane_mailbox_depth "));
+}
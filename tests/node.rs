@@ -0,0 +1,333 @@
+#![cfg(feature = "node")]
+use async_backplane::node::{Connection, Credentials, Node};
+use async_backplane::prelude::*;
+use futures_lite::future::block_on;
+use futures_lite::StreamExt;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread::spawn;
+use std::time::Duration;
+
+// Connects two nodes over loopback, returning both ends.
+fn pair(n1: &Node, n2: &Node) -> (Connection, Connection) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let n = n1.clone();
+    let t = spawn(move || n.accept(&listener).unwrap());
+    let c2 = n2.connect(addr).unwrap();
+    (t.join().unwrap(), c2)
+}
+
+// Frames are handled in order, so once a lookup is answered,
+// everything sent before it has been too.
+fn sync(conn: &Connection) {
+    conn.lookup("").unwrap();
+}
+
+// Waits for something other threads will do.
+fn eventually(f: impl Fn() -> bool) {
+    for _ in 0..500 {
+        if f() { return; }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("Timed out");
+}
+
+#[test]
+fn handshake() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let (c1, c2) = pair(&n1, &n2);
+    assert_eq!(2, c1.remote_node());
+    assert_eq!(1, c2.remote_node());
+    assert!(c1.is_alive());
+    assert!(c2.is_alive());
+}
+
+#[test]
+fn lookup_unknown() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let (_c1, c2) = pair(&n1, &n2);
+    assert!(c2.lookup("nobody").unwrap().is_none());
+}
+
+#[test]
+fn lookup_async() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (_c1, c2) = pair(&n1, &n2);
+    let remote = block_on(c2.lookup_async("d1")).unwrap().unwrap();
+    assert_eq!(1, remote.device_id().node());
+    assert!(block_on(c2.lookup_async("nobody")).unwrap().is_none());
+}
+
+#[test]
+fn remote_notify() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (_c1, c2) = pair(&n1, &n2);
+    let remote = c2.lookup("d1").unwrap().unwrap();
+    assert_eq!(1, remote.device_id().node());
+    assert!(!remote.device_id().is_local());
+    let mut d2 = Device::new();
+    remote.link_line(d2.line(), LinkMode::Notify).unwrap();
+    let rid = remote.device_id();
+    sync(&c2);
+    d1.disconnect(Some(Fault::Error));
+    assert_eq!(Some(Disconnected(rid, Some(Fault::Error))), block_on(d2.next()));
+}
+
+#[test]
+fn remote_monitor() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let mut d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (_c1, c2) = pair(&n1, &n2);
+    let remote = c2.lookup("d1").unwrap().unwrap();
+    let d2 = Device::new();
+    let did = d2.device_id();
+    remote.link_line(d2.line(), LinkMode::Monitor).unwrap();
+    sync(&c2);
+    d2.disconnect(Some(Fault::Timeout));
+    match block_on(d1.next()) {
+        Some(Disconnected(id, Some(Fault::Timeout))) => {
+            assert_eq!(2, id.node());
+            assert_eq!(format!("2:{:x}", did), format!("{:x}", id));
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn remote_unlink() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (_c1, c2) = pair(&n1, &n2);
+    let remote = c2.lookup("d1").unwrap().unwrap();
+    let d2 = Device::new();
+    remote.link_line(d2.line(), LinkMode::Peer).unwrap();
+    remote.link_line(d2.line(), LinkMode::Notify).unwrap();
+    assert_eq!(Ok(()), remote.unlink_line(&d2.line(), LinkMode::Peer));
+    assert_eq!(Err(LinkError::NotLinked), remote.unlink_line(&d2.line(), LinkMode::Notify));
+    sync(&c2);
+    d1.disconnect(Some(Fault::Error));
+    sync(&c2);
+    assert_eq!(None, d2.receive());
+    remote.link_line(d2.line(), LinkMode::Monitor).unwrap();
+    c2.close();
+    assert_eq!(Err(LinkError::DeviceDown), remote.unlink_line(&d2.line(), LinkMode::Monitor));
+}
+
+#[test]
+fn remote_monitor_twice() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (_c1, c2) = pair(&n1, &n2);
+    let remote = c2.lookup("d1").unwrap().unwrap();
+    let d2 = Device::new();
+    remote.link_line(d2.line(), LinkMode::Monitor).unwrap();
+    remote.link_line(d2.line(), LinkMode::Monitor).unwrap();
+    assert_eq!(1, d2.links().len());
+    assert_eq!(Ok(()), remote.unlink_line(&d2.line(), LinkMode::Monitor));
+    assert!(d2.links().is_empty());
+    assert_eq!(Err(LinkError::NotLinked), remote.unlink_line(&d2.line(), LinkMode::Monitor));
+}
+
+#[test]
+fn remote_watcher_disconnects() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (_c1, c2) = pair(&n1, &n2);
+    let remote = c2.lookup("d1").unwrap().unwrap();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    remote.link_line(d2.line(), LinkMode::Notify).unwrap();
+    remote.link_line(d3.line(), LinkMode::Notify).unwrap();
+    sync(&c2);
+    assert_eq!(1, d1.links().len());
+    d2.disconnect(None);
+    sync(&c2);
+    assert_eq!(1, d1.links().len());
+    d3.disconnect(None);
+    // The other Node stops watching once nobody here is.
+    eventually(|| d1.links().is_empty());
+}
+
+#[test]
+fn connection_lost() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (c1, c2) = pair(&n1, &n2);
+    let remote = c2.lookup("d1").unwrap().unwrap();
+    let mut d2 = Device::new();
+    remote.link_line(d2.line(), LinkMode::Notify).unwrap();
+    c1.close();
+    assert!(!c1.is_alive());
+    let rid = remote.device_id();
+    assert_eq!(Some(Disconnected(rid, Some(Fault::ConnectionLost))), block_on(d2.next()));
+    assert!(remote.link_line(d2.line(), LinkMode::Notify).is_err());
+    assert!(remote.send(Shutdown(d2.device_id())).is_err());
+}
+
+#[test]
+fn remote_send() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let mut d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (_c1, c2) = pair(&n1, &n2);
+    let remote = c2.lookup("d1").unwrap().unwrap();
+    let d2 = Device::new();
+    remote.send(Shutdown(d2.device_id())).unwrap();
+    match block_on(d1.next()) {
+        Some(Shutdown(id)) => assert_eq!(2, id.node()),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn remote_cascade() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (_c1, c2) = pair(&n1, &n2);
+    let remote = c2.lookup("d1").unwrap().unwrap();
+    let rid = remote.device_id();
+    let d2 = Device::new();
+    remote.link_line(d2.line(), LinkMode::Notify).unwrap();
+    sync(&c2);
+    let t = spawn(move || {
+        block_on(d2.manage(Box::pin(futures_lite::future::pending::<Result<(), ()>>())))
+    });
+    d1.disconnect(Some(Fault::Drop));
+    match t.join().unwrap() {
+        Err(Crash::Cascade(id, Fault::Drop)) => assert_eq!(rid, id),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn unix_pair(n1: &Node, n2: &Node, name: &str) -> (std::io::Result<Connection>, std::io::Result<Connection>) {
+    use std::os::unix::net::UnixListener;
+    let path = std::env::temp_dir().join(format!("backplane-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let n = n1.clone();
+    let t = spawn(move || n.accept_unix(&listener));
+    let c2 = n2.connect_unix(&path);
+    let c1 = t.join().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    (c1, c2)
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn unix_links() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (c1, c2) = unix_pair(&n1, &n2, "links");
+    let (c1, c2) = (c1.unwrap(), c2.unwrap());
+    let me = Credentials::current();
+    assert_eq!(Some(me), c1.peer_credentials());
+    assert_eq!(Some(me), c2.peer_credentials());
+    let remote = c2.lookup("d1").unwrap().unwrap();
+    let mut d2 = Device::new();
+    remote.link_line(d2.line(), LinkMode::Notify).unwrap();
+    sync(&c2);
+    d1.disconnect(Some(Fault::Error));
+    let rid = remote.device_id();
+    assert_eq!(Some(Disconnected(rid, Some(Fault::Error))), block_on(d2.next()));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn unix_socket_closed() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    let d1 = Device::new();
+    n1.export("d1", d1.line());
+    let (c1, c2) = unix_pair(&n1, &n2, "closed");
+    let (c1, c2) = (c1.unwrap(), c2.unwrap());
+    let remote = c2.lookup("d1").unwrap().unwrap();
+    let mut d2 = Device::new();
+    remote.link_line(d2.line(), LinkMode::Notify).unwrap();
+    c1.close();
+    let rid = remote.device_id();
+    assert_eq!(Some(Disconnected(rid, Some(Fault::ConnectionLost))), block_on(d2.next()));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn unix_peer_rejected() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    n1.set_peer_check(|peer| peer.uid != Credentials::current().uid);
+    let (c1, c2) = unix_pair(&n1, &n2, "rejected");
+    assert_eq!(std::io::ErrorKind::PermissionDenied, c1.unwrap_err().kind());
+    assert!(c2.is_err());
+}
+
+#[test]
+fn ticks_keep_connections_up() {
+    let (n1, n2) = (Node::new(1), Node::new(2));
+    n1.set_tick_time(Duration::from_millis(100));
+    n2.set_tick_time(Duration::from_millis(100));
+    let (c1, c2) = pair(&n1, &n2);
+    std::thread::sleep(Duration::from_millis(350));
+    assert!(c1.is_alive());
+    assert!(c2.is_alive());
+}
+
+#[test]
+fn hung_node_is_down() {
+    let n1 = Node::new(1);
+    n1.set_tick_time(Duration::from_millis(100));
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    // A peer which says hello, answers one lookup and then hangs.
+    let peer = spawn(move || {
+        let (mut s, _) = listener.accept().unwrap();
+        s.write_all(&[0, 0, 0, 5, 0, 0, 0, 0, 2]).unwrap();
+        let mut buf = [0; 9];
+        s.read_exact(&mut buf).unwrap(); // hello
+        let mut len = [0; 4];
+        s.read_exact(&mut len).unwrap();
+        let mut lookup = vec![0; u32::from_be_bytes(len) as usize];
+        s.read_exact(&mut lookup).unwrap();
+        let mut found = vec![0, 0, 0, 22, 2];
+        found.extend_from_slice(&lookup[1..9]); // lookup id
+        found.extend_from_slice(&[1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 7]);
+        s.write_all(&found).unwrap();
+        s
+    });
+    let conn = n1.connect(addr).unwrap();
+    let remote = conn.lookup("d").unwrap().unwrap();
+    assert_eq!("2:7", format!("{:x}", remote.device_id()));
+    let mut d1 = Device::new();
+    remote.link_line(d1.line(), LinkMode::Notify).unwrap();
+    let _hung = peer.join().unwrap();
+    let rid = remote.device_id();
+    assert_eq!(Some(Disconnected(rid, Some(Fault::NodeDown))), block_on(d1.next()));
+    assert!(!conn.is_alive());
+}
+
+#[test]
+fn lookup_on_hung_node() {
+    let n1 = Node::new(1);
+    n1.set_tick_time(Duration::from_millis(100));
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    // A peer which says hello and then never answers.
+    let peer = spawn(move || {
+        let (mut s, _) = listener.accept().unwrap();
+        s.write_all(&[0, 0, 0, 5, 0, 0, 0, 0, 2]).unwrap();
+        s
+    });
+    let conn = n1.connect(addr).unwrap();
+    let _hung = peer.join().unwrap();
+    assert!(block_on(conn.lookup_async("d")).is_err());
+    assert!(!conn.is_alive());
+}
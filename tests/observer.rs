@@ -0,0 +1,132 @@
+#![cfg(feature = "std")]
+use async_backplane::observer::{self, Observer};
+use async_backplane::prelude::*;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+#[derive(Debug, Eq, PartialEq)]
+enum Event {
+    Created(DeviceID),
+    Linked(DeviceID, DeviceID, LinkMode),
+    Unlinked(DeviceID, DeviceID, LinkMode),
+    SendFailed(DeviceID, Message),
+    Disconnected(DeviceID, Option<Fault>),
+}
+
+#[derive(Default)]
+struct Recorder {
+    events: Mutex<Vec<Event>>,
+}
+
+impl Observer for Recorder {
+    fn created(&self, device: DeviceID) {
+        self.events.lock().unwrap().push(Event::Created(device));
+    }
+    fn linked(&self, device: DeviceID, other: DeviceID, mode: LinkMode) {
+        self.events.lock().unwrap().push(Event::Linked(device, other, mode));
+    }
+    fn unlinked(&self, device: DeviceID, other: DeviceID, mode: LinkMode) {
+        self.events.lock().unwrap().push(Event::Unlinked(device, other, mode));
+    }
+    fn send_failed(&self, device: DeviceID, message: &Message) {
+        self.events.lock().unwrap().push(Event::SendFailed(device, *message));
+    }
+    fn disconnected(&self, device: DeviceID, fault: Option<Fault>) {
+        self.events.lock().unwrap().push(Event::Disconnected(device, fault));
+    }
+}
+
+// Tests run in parallel and DeviceIDs are reused, so tests take
+// turns with the one observer, each starting with no events.
+fn recorder() -> (MutexGuard<'static, ()>, Arc<Recorder>) {
+    static TURN: Mutex<()> = Mutex::new(());
+    static RECORDER: OnceLock<Arc<Recorder>> = OnceLock::new();
+    let turn = TURN.lock().unwrap_or_else(|e| e.into_inner());
+    let recorder = RECORDER
+        .get_or_init(|| {
+            let recorder = Arc::new(Recorder::default());
+            observer::install(recorder.clone());
+            recorder
+        })
+        .clone();
+    recorder.events.lock().unwrap().clear();
+    (turn, recorder)
+}
+
+fn events_for(recorder: &Recorder, did: DeviceID) -> Vec<Event> {
+    let mut events = recorder.events.lock().unwrap();
+    let (mine, theirs) = events.drain(..).partition(|e| match e {
+        Event::Created(d) | Event::SendFailed(d, _) | Event::Disconnected(d, _) => *d == did,
+        Event::Linked(d, _, _) | Event::Unlinked(d, _, _) => *d == did,
+    });
+    *events = theirs;
+    mine
+}
+
+#[test]
+fn device_lifecycle() {
+    let (_turn, recorder) = recorder();
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let i1 = d1.device_id();
+    let i2 = d2.device_id();
+    d1.link(&d2, LinkMode::Peer);
+    d1.unlink(&d2, LinkMode::Notify);
+    d1.link_line(d2.line(), LinkMode::Monitor).unwrap();
+    d1.disconnect(Some(Fault::Error));
+    assert_eq!(
+        vec![
+            Event::Created(i1),
+            Event::Linked(i1, i2, LinkMode::Peer),
+            Event::Unlinked(i1, i2, LinkMode::Notify),
+            Event::Linked(i1, i2, LinkMode::Monitor),
+            Event::Disconnected(i1, Some(Fault::Error)),
+        ],
+        events_for(&recorder, i1)
+    );
+    drop(d2);
+    assert_eq!(
+        vec![Event::Created(i2), Event::Disconnected(i2, Some(Fault::Drop))],
+        events_for(&recorder, i2)
+    );
+    assert_eq!(
+        vec![Event::SendFailed(i1, Disconnected(i2, Some(Fault::Drop)))],
+        events_for(&recorder, i1)
+    );
+}
+
+#[test]
+fn line_links() {
+    let (_turn, recorder) = recorder();
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let i1 = d1.device_id();
+    let i2 = d2.device_id();
+    let (l1, l2) = (d1.line(), d2.line());
+    l1.link_line(l2.clone(), LinkMode::Notify).unwrap();
+    l1.unlink_line(&l2, LinkMode::Notify).unwrap();
+    d1.disconnect(None);
+    assert_eq!(
+        vec![
+            Event::Created(i1),
+            Event::Linked(i1, i2, LinkMode::Notify),
+            Event::Unlinked(i1, i2, LinkMode::Notify),
+            Event::Disconnected(i1, None),
+        ],
+        events_for(&recorder, i1)
+    );
+}
+
+#[test]
+fn send_failed() {
+    let (_turn, recorder) = recorder();
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let i1 = d1.device_id();
+    let line = d1.line();
+    drop(d1);
+    line.send(Shutdown(d2.device_id())).unwrap_err();
+    assert_eq!(
+        Some(&Event::SendFailed(i1, Shutdown(d2.device_id()))),
+        events_for(&recorder, i1).last()
+    );
+}
@@ -1,3 +1,4 @@
+#![cfg(feature = "std")]
 use async_backplane::prelude::*;
 use futures_lite::future::{block_on, pending, ready};
 // use futures_lite::stream::StreamExt;
@@ -62,7 +63,7 @@ fn monitored_device_errors() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Error)))), r3);
 }
 
 #[test]
@@ -83,7 +84,7 @@ fn monitored_device_drops() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Drop)))), r3);
 }
 
 #[test]
@@ -118,7 +119,7 @@ fn peer_device_crashes() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Error)))), r3);
 }
 
 #[test]
@@ -140,7 +141,7 @@ fn peer_device_drops() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Drop)))), r3);
 }
 
 #[test]
@@ -177,7 +178,7 @@ fn monitored_line_errors() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Error)))), r3);
 }
 
 #[test]
@@ -198,7 +199,7 @@ fn monitored_line_drops() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Drop)))), r3);
 }
 
 #[test]
@@ -235,7 +236,7 @@ fn peer_line_crashes() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Error)))), r3);
 }
 
 #[test]
@@ -257,5 +258,5 @@ fn peer_line_drops() {
         panic!()
     }
     let r3 = watch(d3).join().unwrap().unwrap();
-    assert_eq!(Messaged(Disconnected(i2, Some(Fault::Cascade(i1)))), r3);
+    assert_eq!(Messaged(Disconnected(i2, Some(Fault::cascade(i1, Fault::Drop)))), r3);
 }
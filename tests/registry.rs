@@ -0,0 +1,84 @@
+#![cfg(feature = "std")]
+use async_backplane::prelude::*;
+use async_backplane::registry::{self, Edge, Snapshot};
+
+// Tests run in parallel, so we only look at the Devices each test
+// created.
+fn snapshot_of(devices: &[&Device]) -> Snapshot {
+    let ids: Vec<DeviceID> = devices.iter().map(|d| d.device_id()).collect();
+    let mut snapshot = registry::snapshot();
+    snapshot.devices.retain(|n| ids.contains(&n.device_id));
+    snapshot.links.retain(|e| ids.contains(&e.from) || ids.contains(&e.to));
+    snapshot
+}
+
+#[test]
+fn records_links() {
+    registry::enable();
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    d1.set_name("one");
+    d1.link(&d2, LinkMode::Monitor);
+    d1.line().link_line(d3.line(), LinkMode::Peer).unwrap();
+    let snapshot = snapshot_of(&[&d1, &d2, &d3]);
+    assert_eq!(3, snapshot.devices.len());
+    let one = snapshot.devices.iter().find(|n| n.device_id == d1.device_id()).unwrap();
+    assert_eq!(Some("one".to_string()), one.name);
+    let mut expected = vec![
+        Edge { from: d2.device_id(), to: d1.device_id() },
+        Edge { from: d3.device_id(), to: d1.device_id() },
+        Edge { from: d1.device_id(), to: d3.device_id() },
+    ];
+    expected.sort_by_key(|e| (e.from, e.to));
+    assert_eq!(expected, snapshot.links);
+    d1.unlink(&d2, LinkMode::Monitor);
+    assert_eq!(2, snapshot_of(&[&d1, &d2, &d3]).links.len());
+}
+
+#[test]
+fn forgets_disconnected() {
+    registry::enable();
+    let d1 = Device::new();
+    let d2 = Device::new();
+    d1.link(&d2, LinkMode::Peer);
+    let id2 = d2.device_id();
+    d2.disconnect(None);
+    let snapshot = snapshot_of(&[&d1]);
+    assert!(snapshot.devices.iter().all(|n| n.device_id != id2));
+    assert!(snapshot.links.is_empty());
+}
+
+#[test]
+fn forgets_after_partial_unlink() {
+    registry::enable();
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    d1.link(&d2, LinkMode::Peer);
+    d1.link(&d3, LinkMode::Notify);
+    d1.unlink(&d2, LinkMode::Notify);
+    assert_eq!(2, snapshot_of(&[&d1, &d2, &d3]).links.len());
+    d2.disconnect(None);
+    let snapshot = snapshot_of(&[&d1, &d3]);
+    assert_eq!(vec![Edge { from: d1.device_id(), to: d3.device_id() }], snapshot.links);
+}
+
+#[test]
+fn exports() {
+    registry::enable();
+    let d1 = Device::new();
+    let d2 = Device::new();
+    d1.set_name("say \"hi\"");
+    d1.link(&d2, LinkMode::Notify);
+    let snapshot = snapshot_of(&[&d1, &d2]);
+    let (id1, id2) = (usize::from(d1.device_id()), usize::from(d2.device_id()));
+    let dot = snapshot.to_dot();
+    assert!(dot.starts_with("digraph backplane {\n"));
+    assert!(dot.contains(&format!("  \"{:x}\" [label=\"say \\\"hi\\\"\"];\n", id1)));
+    assert!(dot.contains(&format!("  \"{:x}\" -> \"{:x}\";\n", id1, id2)));
+    let json = snapshot.to_json();
+    assert!(json.contains(&format!("{{\"id\":\"{:x}\",\"name\":\"say \\\"hi\\\"\"}}", id1)));
+    assert!(json.contains(&format!("{{\"id\":\"{:x}\",\"name\":null}}", id2)));
+    assert!(json.ends_with(&format!("\"links\":[{{\"from\":\"{:x}\",\"to\":\"{:x}\"}}]}}", id1, id2)));
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "std")]
+use async_backplane::prelude::*;
+use async_backplane::report::{self, CrashReport};
+use futures_lite::future::{block_on, pending};
+use std::sync::{Arc, Mutex, OnceLock};
+
+// Tests run in parallel, so we share one sink and only look at the
+// reports for the Devices each test named, as DeviceIDs are reused.
+fn reports() -> Arc<Mutex<Vec<CrashReport>>> {
+    static REPORTS: OnceLock<Arc<Mutex<Vec<CrashReport>>>> = OnceLock::new();
+    REPORTS
+        .get_or_init(|| {
+            let reports = Arc::new(Mutex::new(Vec::new()));
+            let sink = reports.clone();
+            report::install(Arc::new(move |r| sink.lock().unwrap().push(r)));
+            reports
+        })
+        .clone()
+}
+
+fn reports_for(name: &str) -> Vec<CrashReport> {
+    let reports = reports();
+    let reports = reports.lock().unwrap();
+    reports.iter().filter(|r| r.name.as_deref() == Some(name)).cloned().collect()
+}
+
+#[test]
+fn reports_errors() {
+    reports();
+    let d = Device::new();
+    d.set_name("errors");
+    let did = d.device_id();
+    let ret: Result<(), Crash<&str>> = block_on(d.manage(Box::pin(async { Err("oops") })));
+    assert!(ret.is_err());
+    let reports = reports_for("errors");
+    assert_eq!(1, reports.len());
+    assert_eq!(did, reports[0].device_id);
+    assert_eq!(Fault::Error, reports[0].fault);
+    assert_eq!(Some("\"oops\"".to_string()), reports[0].message);
+    assert!(reports[0].location.is_none());
+    assert!(reports[0].ancestors.is_empty());
+}
+
+#[test]
+fn reports_panics() {
+    reports();
+    let d = Device::new();
+    d.set_name("panics");
+    let ret: Result<(), Crash<()>> = block_on(d.manage(Box::pin(async { panic!("boom") })));
+    assert!(ret.unwrap_err().is_panic());
+    let reports = reports_for("panics");
+    assert_eq!(1, reports.len());
+    assert_eq!(Fault::Error, reports[0].fault);
+    assert_eq!(Some("boom".to_string()), reports[0].message);
+    let location = reports[0].location.clone().unwrap();
+    assert!(location.starts_with("tests/report.rs:"));
+}
+
+#[test]
+fn reports_cascades() {
+    reports();
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let d3 = Device::new();
+    let (id1, id2, id3) = (d1.device_id(), d2.device_id(), d3.device_id());
+    d1.link(&d2, LinkMode::Monitor);
+    d2.link(&d3, LinkMode::Monitor);
+    d1.set_name("cascades-1");
+    d2.set_name("cascades-2");
+    d3.disconnect(Some(Fault::Error));
+    let ret: Result<(), Crash<()>> = block_on(d2.manage(pending()));
+    assert!(ret.unwrap_err().is_cascade());
+    let ret: Result<(), Crash<()>> = block_on(d1.manage(pending()));
+    assert!(ret.unwrap_err().is_cascade());
+    let reports = reports_for("cascades-1");
+    assert_eq!(1, reports.len());
+    assert_eq!(id1, reports[0].device_id);
+    assert_eq!(Fault::cascade(id2, Fault::cascade(id3, Fault::Error)), reports[0].fault);
+    assert_eq!(vec![id2, id3], reports[0].ancestors);
+    assert_eq!(vec![id3], reports_for("cascades-2")[0].ancestors);
+}
+
+#[test]
+fn no_report_on_success() {
+    reports();
+    let d = Device::new();
+    d.set_name("success");
+    let ret: Result<(), Crash<()>> = block_on(d.manage(Box::pin(async { Ok(()) })));
+    assert!(ret.is_ok());
+    assert!(reports_for("success").is_empty());
+}
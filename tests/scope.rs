@@ -0,0 +1,142 @@
+#![cfg(feature = "std")]
+use async_backplane::prelude::*;
+use futures_lite::future::{block_on, pending, ready};
+use std::sync::{Arc, Mutex};
+
+fn gone(lines: &[Line]) -> bool {
+    lines.iter().all(|l| !l.is_alive())
+}
+
+#[test]
+fn body_succeeds() {
+    let mut d = Device::new();
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let l2 = lines.clone();
+    let r: Result<usize, Crash<()>> = block_on(d.scope(|scope| async move {
+        let mut lines = l2.lock().unwrap();
+        lines.push(scope.spawn(pending::<Result<(), ()>>()).unwrap());
+        lines.push(scope.spawn(ready(Ok::<(), ()>(()))).unwrap());
+        Ok(42)
+    }));
+    assert_eq!(42, r.unwrap());
+    let lines = lines.lock().unwrap();
+    assert!(gone(&lines));
+    for line in lines.iter() {
+        assert_eq!(None, block_on(line.disconnected()));
+    }
+    assert_eq!(None, d.receive());
+}
+
+#[test]
+fn body_errors() {
+    let mut d = Device::new();
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let l2 = lines.clone();
+    let r: Result<(), Crash<usize>> = block_on(d.scope(|scope| async move {
+        l2.lock().unwrap().push(scope.spawn(pending::<Result<(), ()>>()).unwrap());
+        Err(42)
+    }));
+    if let Crash::Error(val) = r.unwrap_err() {
+        assert_eq!(42, val);
+    } else {
+        panic!()
+    }
+    assert!(gone(&lines.lock().unwrap()));
+    assert_eq!(None, d.receive());
+}
+
+#[test]
+fn body_panics() {
+    let mut d = Device::new();
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let l2 = lines.clone();
+    let r: Result<(), Crash<()>> = block_on(d.scope(|scope| async move {
+        l2.lock().unwrap().push(scope.spawn(pending::<Result<(), ()>>()).unwrap());
+        panic!("oh no")
+    }));
+    assert!(r.unwrap_err().is_panic());
+    assert!(gone(&lines.lock().unwrap()));
+    assert_eq!(None, d.receive());
+}
+
+#[test]
+fn child_faults() {
+    let mut d = Device::new();
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let l2 = lines.clone();
+    let r: Result<(), Crash<()>> = block_on(d.scope(|scope| async move {
+        {
+            let mut lines = l2.lock().unwrap();
+            lines.push(scope.spawn(pending::<Result<(), ()>>()).unwrap());
+            lines.push(scope.spawn(ready(Err::<(), ()>(()))).unwrap());
+        }
+        pending().await
+    }));
+    let lines = lines.lock().unwrap();
+    if let Crash::Cascade(did, fault) = r.unwrap_err() {
+        assert_eq!(lines[1].device_id(), did);
+        assert_eq!(Fault::Error, fault);
+    } else {
+        panic!()
+    }
+    assert!(gone(&lines));
+    assert_eq!(None, block_on(lines[0].disconnected()));
+    assert_eq!(Some(Fault::Error), block_on(lines[1].disconnected()));
+    assert_eq!(None, d.receive());
+}
+
+#[test]
+fn shutdown_requested() {
+    let mut d = Device::new();
+    let d2 = Device::new();
+    let i2 = d2.device_id();
+    d.line().send(Shutdown(i2)).unwrap();
+    let r: Result<(), Crash<()>> = block_on(d.scope(|scope| async move {
+        scope.spawn(pending::<Result<(), ()>>()).unwrap();
+        pending().await
+    }));
+    if let Crash::PowerOff(did) = r.unwrap_err() {
+        assert_eq!(i2, did);
+    } else {
+        panic!()
+    }
+    assert_eq!(None, d.receive());
+}
+
+#[test]
+fn leaves_other_messages() {
+    let mut d = Device::new();
+    let d2 = Device::new();
+    let i2 = d2.device_id();
+    let line = d.line();
+    let r: Result<(), Crash<()>> = block_on(d.scope(|scope| async move {
+        scope.spawn(pending::<Result<(), ()>>()).unwrap();
+        line.send(Shutdown(i2)).unwrap();
+        Ok(())
+    }));
+    assert_eq!((), r.unwrap());
+    assert_eq!(Some(Shutdown(i2)), d.receive());
+    assert_eq!(None, d.receive());
+}
+
+#[test]
+fn spawn_after_leaving() {
+    let mut d = Device::new();
+    let r: Result<Scope, Crash<()>> = block_on(d.scope(|scope| async move { Ok(scope) }));
+    let scope = r.unwrap();
+    assert_eq!(d.device_id(), scope.device_id());
+    assert_eq!(Err(LinkError::DeviceDown), scope.spawn(ready(Ok::<(), ()>(()))));
+    assert_eq!(None, d.receive());
+    assert!(d.links().is_empty());
+}
+
+#[test]
+fn device_survives() {
+    let mut d = Device::new();
+    let line = d.line();
+    let r: Result<(), Crash<()>> = block_on(d.scope(|_| async { Err(()) }));
+    assert!(r.unwrap_err().is_error());
+    assert!(line.is_alive());
+    let r: Result<(), Crash<()>> = block_on(d.scope(|_| async { Ok(()) }));
+    assert_eq!((), r.unwrap());
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "serde")]
+use async_backplane::prelude::*;
+use futures_lite::future::block_on;
+
+fn roundtrip<T>(value: T) -> T
+where T: serde::Serialize + serde::de::DeserializeOwned {
+    let json = serde_json::to_string(&value).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn messages() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let fault = Fault::cascade(d2.device_id(), Fault::Timeout);
+    let messages = [
+        Disconnected(d1.device_id(), None),
+        Disconnected(d1.device_id(), Some(Fault::Error)),
+        Disconnected(d1.device_id(), Some(fault)),
+        Shutdown(d2.device_id()),
+    ];
+    for message in messages.iter() {
+        assert_eq!(*message, roundtrip(*message));
+    }
+}
+
+#[test]
+fn link_modes_and_errors() {
+    for mode in [Monitor, Notify, Peer].iter() {
+        assert_eq!(*mode, roundtrip(*mode));
+    }
+    assert_eq!("\"Peer\"", serde_json::to_string(&Peer).unwrap());
+    assert_eq!(LinkError::LinkDown, roundtrip(LinkError::LinkDown));
+}
+
+#[test]
+fn device_ids() {
+    let d = Device::new();
+    let did = d.device_id();
+    let json = serde_json::to_value(did).unwrap();
+    assert_eq!(0, json["node"]);
+    assert_eq!(usize::from(did), json["id"].as_u64().unwrap() as usize);
+    assert_eq!(did, roundtrip(did));
+}
+
+#[test]
+fn crash_summaries() {
+    let d = Device::new();
+    let crash = block_on(d.manage(Box::pin(async { Err::<(), _>("oops".to_string()) }))).unwrap_err();
+    let summary = crash.summary();
+    assert_eq!(CrashSummary::Error("oops".to_string()), roundtrip(summary));
+
+    let d = Device::new();
+    let crash = block_on(d.manage(Box::pin(async { panic!("boom") as Result<(), ()> }))).unwrap_err();
+    match CrashSummary::from(crash) {
+        CrashSummary::Panic(text) => assert!(text.contains("boom")),
+        other => panic!("{:?}", other),
+    }
+}
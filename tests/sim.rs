@@ -0,0 +1,103 @@
+#![cfg(feature = "test-support")]
+use async_backplane::prelude::*;
+use async_backplane::sim::Sim;
+use futures_lite::future::pending;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+#[test]
+fn time_is_virtual() {
+    let mut sim = Sim::new(1);
+    let timer = sim.timer();
+    let log = Rc::new(RefCell::new(Vec::new()));
+    for secs in [3600, 60, 1].iter() {
+        let (timer, log) = (timer.clone(), log.clone());
+        let secs = *secs;
+        sim.spawn(async move {
+            timer.delay(Duration::from_secs(secs)).await;
+            log.borrow_mut().push(secs);
+        });
+    }
+    assert_eq!(0, sim.run());
+    assert_eq!(vec![1, 60, 3600], *log.borrow());
+    assert_eq!(Duration::from_secs(3600), sim.now());
+}
+
+#[test]
+fn run_for_stops_at_the_limit() {
+    let mut sim = Sim::new(1);
+    let timer = sim.timer();
+    sim.spawn(async move { timer.delay(Duration::from_secs(10)).await });
+    assert_eq!(1, sim.run_for(Duration::from_secs(5)));
+    assert_eq!(Duration::from_secs(5), sim.now());
+    assert_eq!(0, sim.run_for(Duration::from_secs(5)));
+    assert_eq!(Duration::from_secs(10), sim.now());
+}
+
+#[test]
+fn stuck_tasks_are_counted() {
+    let mut sim = Sim::new(1);
+    sim.spawn(pending());
+    sim.spawn(async {});
+    assert_eq!(1, sim.run());
+}
+
+#[test]
+fn managed_timeout() {
+    let mut sim = Sim::new(1);
+    let timer = sim.timer();
+    let d = Device::new();
+    let line = d.line();
+    let crashed = Rc::new(RefCell::new(None));
+    let c = crashed.clone();
+    sim.spawn(async move {
+        let r: Result<(), Crash<()>> =
+            d.manage_timeout(pending(), &timer, Duration::from_secs(30)).await;
+        *c.borrow_mut() = Some(r.unwrap_err().is_timeout());
+    });
+    assert_eq!(0, sim.run());
+    assert_eq!(Some(true), *crashed.borrow());
+    assert_eq!(Duration::from_secs(30), sim.now());
+    assert!(!line.is_alive());
+}
+
+// Peers a row of Devices, faults one in the middle and records the
+// order in which the others crash.
+fn cascade(seed: u64) -> Vec<usize> {
+    let mut sim = Sim::new(seed);
+    let timer = sim.timer();
+    let devices: Vec<Device> = (0..6).map(|_| Device::new()).collect();
+    for pair in devices.windows(2) {
+        pair[0].link(&pair[1], LinkMode::Peer);
+    }
+    let order = Rc::new(RefCell::new(Vec::new()));
+    for (i, d) in devices.into_iter().enumerate() {
+        let (timer, order) = (timer.clone(), order.clone());
+        sim.spawn(async move {
+            let r: Result<(), Crash<()>> = if i == 3 {
+                d.manage(Box::pin(async move {
+                    timer.delay(Duration::from_secs(1)).await;
+                    Err(())
+                }))
+                .await
+            } else {
+                d.manage(pending()).await
+            };
+            assert!(r.is_err());
+            order.borrow_mut().push(i);
+        });
+    }
+    assert_eq!(0, sim.run());
+    let order = order.borrow().clone();
+    order
+}
+
+#[test]
+fn cascades_are_reproducible() {
+    for seed in 1..20 {
+        let order = cascade(seed);
+        assert_eq!(3, order[0]);
+        assert_eq!(order, cascade(seed));
+    }
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "test-support")]
+use async_backplane::prelude::*;
+use async_backplane::test_support::*;
+use futures_lite::future::{block_on, pending};
+use std::thread::spawn;
+
+#[test]
+fn injected_fault_cascades() {
+    let d = Device::new();
+    let line = d.line();
+    let id = Device::new().device_id();
+    inject_fault(&line, id, Fault::Error).unwrap();
+    let r: Result<(), Crash<()>> = block_on(d.manage(pending()));
+    if let Crash::Cascade(did, fault) = r.unwrap_err() {
+        assert_eq!(id, did);
+        assert_eq!(Fault::Error, fault);
+    } else {
+        panic!()
+    }
+    assert_eq!(Some(Fault::cascade(id, Fault::Error)), block_on(line.disconnected()));
+}
+
+#[test]
+fn injected_shutdown_powers_off() {
+    let d = Device::new();
+    let line = d.line();
+    let id = Device::new().device_id();
+    inject_shutdown(&line, id).unwrap();
+    let r: Result<(), Crash<()>> = block_on(d.manage(pending()));
+    if let Crash::PowerOff(did) = r.unwrap_err() {
+        assert_eq!(id, did);
+    } else {
+        panic!()
+    }
+    assert_eq!(None, block_on(line.disconnected()));
+}
+
+#[test]
+fn triggered_panic() {
+    let d = Device::new();
+    let line = d.line();
+    let trigger = Trigger::new();
+    let f = trigger.wrap(pending::<Result<(), ()>>());
+    let f = async move { f.await.unwrap() };
+    let t = spawn(move || block_on(d.manage(Box::pin(f))));
+    trigger.fire(Injection::Panic);
+    assert!(t.join().unwrap().unwrap_err().is_panic());
+    assert_eq!(Some(Fault::Error), block_on(line.disconnected()));
+}
+
+#[test]
+fn triggered_drop() {
+    let d = Device::new();
+    let line = d.line();
+    let trigger = Trigger::new();
+    let f = trigger.wrap(d.manage(pending::<Result<(), ()>>()));
+    let t = spawn(move || block_on(f));
+    trigger.fire(Injection::Drop);
+    assert!(t.join().unwrap().is_none());
+    assert_eq!(Some(Fault::Drop), block_on(line.disconnected()));
+}
+
+#[test]
+fn triggered_on_many_tasks() {
+    let trigger = Trigger::new();
+    let wrap = |d: Device| {
+        trigger.wrap(async move {
+            d.ready();
+            pending::<()>().await;
+            drop(d);
+        })
+    };
+    let (d1, d2) = (Device::new(), Device::new());
+    let (l1, l2) = (d1.line(), d2.line());
+    let (f1, f2) = (wrap(d1), wrap(d2));
+    let t1 = spawn(move || block_on(f1));
+    let t2 = spawn(move || block_on(f2));
+    // Both are waiting before we fire.
+    block_on(l1.ready()).unwrap();
+    block_on(l2.ready()).unwrap();
+    trigger.fire(Injection::Drop);
+    assert!(t1.join().unwrap().is_none());
+    assert!(t2.join().unwrap().is_none());
+    assert_eq!(Some(Fault::Drop), block_on(l1.disconnected()));
+    assert_eq!(Some(Fault::Drop), block_on(l2.disconnected()));
+}
+
+// Which Device got which kind of message, as the sender differs
+// between runs.
+fn chaos_run(seed: u64) -> Vec<(usize, Option<Option<Fault>>)> {
+    let devices: Vec<Device> = (0..5).map(|_| Device::new()).collect();
+    let mut chaos = Chaos::new(seed);
+    for d in devices.iter() {
+        chaos.add(d.line());
+    }
+    let mut picks = Vec::new();
+    for _ in 0..10 {
+        let (did, message) = chaos.step().unwrap();
+        let idx = devices.iter().position(|d| d.device_id() == did).unwrap();
+        assert_eq!(chaos.device_id(), message.sender());
+        assert_eq!(Some(message), devices[idx].receive());
+        let kind = match message {
+            Disconnected(_, fault) => Some(fault),
+            Shutdown(_) | Tick(_) => None,
+        };
+        picks.push((idx, kind));
+    }
+    picks
+}
+
+#[test]
+fn chaos_is_deterministic() {
+    assert_eq!(chaos_run(42), chaos_run(42));
+    assert_ne!(chaos_run(42), chaos_run(7));
+}
+
+#[test]
+fn chaos_skips_the_dead() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let mut chaos = Chaos::new(1);
+    chaos.add(d1.line());
+    chaos.add(d2.line());
+    let i2 = d2.device_id();
+    d1.disconnect(None);
+    for _ in 0..5 {
+        assert_eq!(i2, chaos.step().unwrap().0);
+    }
+    d2.disconnect(None);
+    assert_eq!(None, chaos.step());
+}
@@ -0,0 +1,100 @@
+#![cfg(feature = "std")]
+use async_backplane::prelude::*;
+use futures_lite::future::{block_on, pending, ready, Pending, Ready};
+use std::time::Duration;
+
+fn expired(_: Duration) -> Ready<()> {
+    ready(())
+}
+
+fn never(_: Duration) -> Pending<()> {
+    pending()
+}
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn watch_times_out() {
+    let mut d = Device::new();
+    let r: Result<Watched<()>, Crash<()>> = block_on(d.watch_timeout(pending(), &expired, TIMEOUT));
+    assert!(r.unwrap_err().is_timeout());
+}
+
+#[test]
+fn watch_completes_in_time() {
+    let mut d = Device::new();
+    let r: Result<Watched<()>, Crash<()>> = block_on(d.watch_timeout(ready(()), &never, TIMEOUT));
+    assert_eq!(Completed(()), r.unwrap());
+}
+
+#[test]
+fn watch_prefers_messages() {
+    let mut d1 = Device::new();
+    let d2 = Device::new();
+    let i2 = d2.device_id();
+    d1.link(&d2, LinkMode::Monitor);
+    d2.disconnect(None);
+    let r: Result<Watched<()>, Crash<()>> =
+        block_on(d1.watch_timeout(pending(), &expired, TIMEOUT));
+    assert_eq!(Messaged(Disconnected(i2, None)), r.unwrap());
+}
+
+#[test]
+fn manage_times_out() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let i1 = d1.device_id();
+    d2.link(&d1, LinkMode::Monitor);
+    let r: Result<(), Crash<()>> = block_on(d1.manage_timeout(pending(), &expired, TIMEOUT));
+    assert!(r.unwrap_err().is_timeout());
+    assert_eq!(Some(Disconnected(i1, Some(Fault::Timeout))), d2.receive());
+}
+
+#[test]
+fn manage_completes_in_time() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let i1 = d1.device_id();
+    d2.link(&d1, LinkMode::Monitor);
+    let r: Result<(), Crash<()>> = block_on(d1.manage_timeout(ready(Ok(())), &never, TIMEOUT));
+    assert_eq!((), r.unwrap());
+    assert_eq!(Some(Disconnected(i1, None)), d2.receive());
+}
+
+#[test]
+fn part_manage_times_out() {
+    let d = Device::new();
+    let line = d.line();
+    let r: Result<(Device, ()), Crash<()>> =
+        block_on(d.part_manage_timeout(pending(), &expired, TIMEOUT));
+    assert!(r.unwrap_err().is_timeout());
+    assert_eq!(Some(Fault::Timeout), block_on(line.disconnected()));
+}
+
+#[test]
+fn part_manage_completes_in_time() {
+    let d = Device::new();
+    let line = d.line();
+    let r: Result<(Device, ()), Crash<()>> =
+        block_on(d.part_manage_timeout(ready(Ok(())), &never, TIMEOUT));
+    let (d, ()) = r.unwrap();
+    assert!(line.is_alive());
+    drop(d);
+}
+
+#[test]
+fn timeout_cascades() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let i1 = d1.device_id();
+    d2.link(&d1, LinkMode::Monitor);
+    let r: Result<(), Crash<()>> = block_on(d1.manage_timeout(pending(), &expired, TIMEOUT));
+    assert!(r.unwrap_err().is_timeout());
+    let r: Result<(), Crash<()>> = block_on(d2.manage(pending()));
+    if let Crash::Cascade(did, fault) = r.unwrap_err() {
+        assert_eq!(i1, did);
+        assert_eq!(Fault::Timeout, fault);
+    } else {
+        panic!()
+    }
+}
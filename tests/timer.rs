@@ -0,0 +1,111 @@
+#![cfg(feature = "std")]
+use async_backplane::prelude::*;
+use async_backplane::timer::{interval, send_after};
+use futures_lite::future::{block_on, pending, poll_once, ready, Boxed};
+use futures_lite::{pin, FutureExt};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const EVERY: Duration = Duration::from_secs(5);
+
+// Expires immediately `count` times, then never again.
+fn ticks(count: usize) -> impl Fn(Duration) -> Boxed<()> {
+    let left = Arc::new(AtomicUsize::new(count));
+    move |_| {
+        if left.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_ok() {
+            ready(()).boxed()
+        } else {
+            pending().boxed()
+        }
+    }
+}
+
+#[test]
+fn send_after_sends() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let (_, fut) = send_after(ticks(1), d1.line(), Shutdown(d2.device_id()), EVERY);
+    block_on(fut);
+    assert_eq!(Some(Shutdown(d2.device_id())), d1.receive());
+    assert_eq!(None, d1.receive());
+}
+
+#[test]
+fn send_after_waits() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let (_, fut) = send_after(ticks(0), d1.line(), Shutdown(d2.device_id()), EVERY);
+    pin!(fut);
+    assert_eq!(None, block_on(poll_once(&mut fut)));
+    assert_eq!(None, d1.receive());
+}
+
+#[test]
+fn send_after_cancels() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let (timer_ref, fut) = send_after(ticks(0), d1.line(), Shutdown(d2.device_id()), EVERY);
+    pin!(fut);
+    assert_eq!(None, block_on(poll_once(&mut fut)));
+    timer_ref.cancel();
+    assert!(timer_ref.is_cancelled());
+    assert_eq!(Some(()), block_on(poll_once(&mut fut)));
+    assert_eq!(None, d1.receive());
+}
+
+#[test]
+fn send_after_disconnected() {
+    let d1 = Device::new();
+    let d2 = Device::new();
+    let line = d1.line();
+    drop(d1);
+    let (_, fut) = send_after(ticks(1), line, Shutdown(d2.device_id()), EVERY);
+    block_on(fut);
+}
+
+#[test]
+fn interval_ticks_until_cancelled() {
+    let d1 = Device::new();
+    let i1 = d1.device_id();
+    let (timer_ref, fut) = interval(ticks(3), d1.line(), EVERY);
+    pin!(fut);
+    assert_eq!(None, block_on(poll_once(&mut fut)));
+    for _ in 0..3 {
+        assert_eq!(Some(Tick(i1)), d1.receive());
+    }
+    assert_eq!(None, d1.receive());
+    timer_ref.cancel();
+    assert_eq!(Some(()), block_on(poll_once(&mut fut)));
+}
+
+#[test]
+fn interval_stops_on_disconnect() {
+    let d1 = Device::new();
+    let line = d1.line();
+    drop(d1);
+    let (timer_ref, fut) = interval(ticks(usize::MAX), line, EVERY);
+    block_on(fut);
+    assert!(!timer_ref.is_cancelled());
+}
+
+#[test]
+fn managed_ignores_ticks() {
+    let d1 = Device::new();
+    let (_, ticker) = interval(ticks(3), d1.line(), EVERY);
+    pin!(ticker);
+    assert_eq!(None, block_on(poll_once(&mut ticker)));
+    let r: Result<(), Crash<()>> = block_on(d1.manage(ready(Ok(()))));
+    assert!(r.is_ok());
+}
+
+#[test]
+fn watched_ticks() {
+    let mut d1 = Device::new();
+    let i1 = d1.device_id();
+    let (_, ticker) = interval(ticks(1), d1.line(), EVERY);
+    pin!(ticker);
+    assert_eq!(None, block_on(poll_once(&mut ticker)));
+    let r: Result<Watched<()>, Crash<()>> = block_on(d1.watch(pending()));
+    assert_eq!(Messaged(Tick(i1)), r.unwrap());
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "tracing")]
+use async_backplane::prelude::*;
+use futures_lite::future::{block_on, pending};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+// Records each event as a line of `name=value` pairs.
+#[derive(Clone, Default)]
+struct Recorder {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+struct Line(String);
+
+impl Visit for Line {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push_str(&format!("{}={:?} ", field.name(), value));
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+    fn new_span(&self, _: &Attributes<'_>) -> Id { Id::from_u64(1) }
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+    fn event(&self, event: &Event<'_>) {
+        let mut line = Line(String::new());
+        event.record(&mut line);
+        self.events.lock().unwrap().push(line.0);
+    }
+    fn enter(&self, _: &Id) {}
+    fn exit(&self, _: &Id) {}
+}
+
+fn recorded<F: FnOnce()>(f: F) -> Vec<String> {
+    let recorder = Recorder::default();
+    let events = recorder.events.clone();
+    tracing::subscriber::with_default(recorder, f);
+    let events = events.lock().unwrap();
+    events.clone()
+}
+
+fn find<'a>(events: &'a [String], message: &str, did: DeviceID) -> Option<&'a String> {
+    let message = format!("message={} ", message);
+    let did = format!("device_id={} ", did);
+    events.iter().find(|e| e.contains(&message) && e.contains(&did))
+}
+
+#[test]
+fn lifecycle() {
+    let mut ids = Vec::new();
+    let events = recorded(|| {
+        let d1 = Device::new();
+        let d2 = Device::new();
+        d1.link(&d2, LinkMode::Peer);
+        ids.push(d1.device_id());
+        ids.push(d2.device_id());
+        d1.disconnect(Some(Fault::Error));
+    });
+    assert!(find(&events, "created", ids[0]).is_some());
+    assert!(find(&events, "created", ids[1]).is_some());
+    assert!(find(&events, "linked", ids[0]).unwrap().contains("mode=Peer"));
+    assert!(find(&events, "disconnected", ids[0]).unwrap().contains("fault=Error"));
+    assert!(find(&events, "sent", ids[1]).is_some());
+    assert!(find(&events, "disconnected", ids[1]).unwrap().contains("fault=Drop"));
+}
+
+#[test]
+fn cascade() {
+    let mut ids = Vec::new();
+    let events = recorded(|| {
+        let d1 = Device::new();
+        let d2 = Device::new();
+        d2.link(&d1, LinkMode::Monitor);
+        ids.push(d1.device_id());
+        ids.push(d2.device_id());
+        d1.disconnect(Some(Fault::Error));
+        let r: Result<(), Crash<()>> = block_on(d2.manage(pending()));
+        assert!(r.is_err());
+    });
+    let cascaded = find(&events, "cascaded", ids[1]).unwrap();
+    assert!(cascaded.contains(&format!("by={} ", ids[0])));
+    let fault = format!("fault=Cascade({:?}, ", ids[0]);
+    assert!(find(&events, "disconnected", ids[1]).unwrap().contains(&fault));
+}
+
+#[test]
+fn send_failed() {
+    let mut ids = Vec::new();
+    let events = recorded(|| {
+        let d1 = Device::new();
+        let d2 = Device::new();
+        ids.push(d1.device_id());
+        let line = d1.line();
+        drop(d1);
+        assert!(line.send(Shutdown(d2.device_id())).is_err());
+    });
+    assert!(find(&events, "send failed", ids[0]).is_some());
+}
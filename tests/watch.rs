@@ -1,3 +1,4 @@
+#![cfg(feature = "std")]
 use async_backplane::prelude::*;
 use futures_lite::future::{block_on, ready};
 use std::thread::{spawn, JoinHandle};